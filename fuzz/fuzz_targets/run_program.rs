@@ -0,0 +1,18 @@
+//! Run with `cargo +nightly fuzz run run_program` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use synacor::{Input, Machine, Output};
+
+const FUZZ_INSTRUCTION_BUDGET: u64 = 10_000;
+
+fuzz_target!(|program: &[u8]| {
+    let mut machine = Machine::new(program);
+    machine.input = Input::Reader(Box::new(std::io::empty()));
+    machine.output = Output::Writer(Box::new(std::io::sink()));
+    machine.max_instructions = Some(FUZZ_INSTRUCTION_BUDGET);
+    machine.game_aliases = None;
+    machine.halt_on_eof = true;
+    let _ = machine.run();
+});
@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{decode_at, instructions, Instruction, Location, Machine, VmError};
+
+#[derive(Debug, Default)]
+pub struct JumpTargets {
+    pub fixed: BTreeMap<usize, usize>,
+    pub dynamic: Vec<(usize, Instruction)>,
+}
+
+pub fn jump_targets(mem: &[u16]) -> JumpTargets {
+    let mut targets = JumpTargets::default();
+    for (addr, instruction) in instructions(mem)
+        .filter_map(|(addr, decoded)| decoded.ok().map(|instruction| (addr, instruction)))
+    {
+        let location = match instruction {
+            Instruction::Jmp(location)
+            | Instruction::Jt(_, location)
+            | Instruction::Jf(_, location)
+            | Instruction::Call(location) => location,
+            _ => continue,
+        };
+
+        match location {
+            Location::Address(target) => *targets.fixed.entry(target.0).or_insert(0) += 1,
+            Location::Register(_) => targets.dynamic.push((addr, instruction)),
+        }
+    }
+
+    targets
+}
+
+pub fn checksum(words: &[u16]) -> u32 {
+    words
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .fold(0x811c9dc5, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+        })
+}
+
+impl Machine {
+    pub fn reachable_code(&self) -> BTreeSet<usize> {
+        self.walk_reachable().0
+    }
+
+    pub fn reachable_decode_errors(&self) -> Vec<(usize, VmError)> {
+        self.walk_reachable().1.into_iter().collect()
+    }
+
+    fn walk_reachable(&self) -> (BTreeSet<usize>, BTreeMap<usize, VmError>) {
+        let program = self.program();
+        let mut reachable = BTreeSet::new();
+        let mut errors = BTreeMap::new();
+        let mut worklist = vec![0];
+        while let Some(addr) = worklist.pop() {
+            if addr >= program.len() || errors.contains_key(&addr) || !reachable.insert(addr) {
+                continue;
+            }
+            let instruction = match decode_at(program, addr) {
+                Ok(instruction) => instruction,
+                Err(err) => {
+                    reachable.remove(&addr);
+                    errors.insert(addr, err);
+                    continue;
+                }
+            };
+
+            let next = addr + instruction.width();
+            match instruction {
+                Instruction::Halt | Instruction::Ret => {}
+                Instruction::Jmp(location) => {
+                    if let Location::Address(target) = location {
+                        worklist.push(target.0);
+                    }
+                }
+                Instruction::Jt(_, location)
+                | Instruction::Jf(_, location)
+                | Instruction::Call(location) => {
+                    if let Location::Address(target) = location {
+                        worklist.push(target.0);
+                    }
+                    worklist.push(next);
+                }
+                _ => worklist.push(next),
+            }
+        }
+
+        (reachable, errors)
+    }
+}
+
+pub fn codes_in(line: &str) -> Vec<&str> {
+    line.split(|ch: char| !ch.is_ascii_alphanumeric())
+        .filter(|word| {
+            word.len() == 12
+                && word.chars().any(|ch| ch.is_ascii_uppercase())
+                && word.chars().any(|ch| ch.is_ascii_lowercase())
+        })
+        .collect()
+}
+
+pub fn find(mem: &[u16], pattern: &[u16]) -> Vec<usize> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+
+    mem.windows(pattern.len())
+        .enumerate()
+        .filter(|&(_, window)| window == pattern)
+        .map(|(addr, _)| addr)
+        .collect()
+}
+
+#[test]
+fn finds_word_patterns() {
+    let mem = [1, 2, 3, 1, 2, 1, 2, 3];
+    assert_eq!(find(&mem, &[1, 2, 3]), [0, 5]);
+    assert!(find(&mem, &[4]).is_empty());
+}
+
+#[test]
+fn reachable_code_skips_data_after_jump() {
+    let program = [6, 5, 19, 65, 0, 7, 32768, 9, 0, 0];
+    let machine = crate::test_util::machine(&program);
+    assert_eq!(machine.reachable_code(), BTreeSet::from([0, 5, 8, 9]));
+}
+
+#[test]
+fn reports_decode_errors_only_in_reachable_code() {
+    let program = [6, 4, 99, 0, 6, 6, 6, 0xffff, 0];
+    let machine = crate::test_util::machine(&program);
+    assert_eq!(
+        machine.reachable_decode_errors(),
+        [(
+            6,
+            VmError::BadOperand {
+                addr: 6,
+                mnemonic: "jmp",
+                operand: 1,
+                value: 0xffff,
+            }
+        )]
+    );
+}
+
+#[test]
+fn spots_challenge_codes() {
+    assert_eq!(
+        codes_in(r#"You find yourself writing "HIVwRqJmDqLg" on the tablet."#),
+        ["HIVwRqJmDqLg"]
+    );
+    assert!(codes_in("Congratulations, adventurer, nothing here").is_empty());
+}
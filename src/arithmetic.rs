@@ -0,0 +1,132 @@
+use proptest::prelude::*;
+
+use crate::{test_util::machine, VmError};
+
+const R0: u16 = 32768;
+const R1: u16 = 32769;
+
+fn execute(words: &[u16]) -> u16 {
+    let mut machine = machine(words);
+    machine.step().unwrap();
+    machine.registers()[0]
+}
+
+proptest! {
+    #[test]
+    fn add_matches_reference(a in 0..32768u16, b in 0..32768u16) {
+        let result = execute(&[9, R0, a, b]);
+        prop_assert!(result <= 0x7fff);
+        prop_assert_eq!(result as u32, (a as u32 + b as u32) % 32768);
+    }
+
+    #[test]
+    fn mult_matches_reference(a in 0..32768u16, b in 0..32768u16) {
+        let result = execute(&[10, R0, a, b]);
+        prop_assert!(result <= 0x7fff);
+        prop_assert_eq!(result as u32, (a as u32 * b as u32) % 32768);
+    }
+
+    #[test]
+    fn mod_matches_reference(a in 0..32768u16, b in 1..32768u16) {
+        let result = execute(&[11, R0, a, b]);
+        prop_assert!(result <= 0x7fff);
+        prop_assert_eq!(result, a % b);
+    }
+
+    #[test]
+    fn and_matches_reference(a in 0..32768u16, b in 0..32768u16) {
+        let result = execute(&[12, R0, a, b]);
+        prop_assert!(result <= 0x7fff);
+        prop_assert_eq!(result, a & b);
+    }
+
+    #[test]
+    fn or_matches_reference(a in 0..32768u16, b in 0..32768u16) {
+        let result = execute(&[13, R0, a, b]);
+        prop_assert!(result <= 0x7fff);
+        prop_assert_eq!(result, a | b);
+    }
+
+    #[test]
+    fn eq_matches_reference(a in 0..32768u16, b in 0..32768u16) {
+        prop_assert_eq!(execute(&[4, R0, a, b]), (a == b) as u16);
+    }
+
+    #[test]
+    fn gt_matches_reference(a in 0..32768u16, b in 0..32768u16) {
+        prop_assert_eq!(execute(&[5, R0, a, b]), (a > b) as u16);
+    }
+
+    #[test]
+    fn not_matches_reference(a in 0..32768u16) {
+        let result = execute(&[14, R0, a]);
+        prop_assert!(result <= 0x7fff);
+        prop_assert_eq!(result, 0x7fff - a);
+    }
+}
+
+#[test]
+fn mod_by_zero_is_an_error() {
+    let mut machine = machine(&[11, R0, 5, 0]);
+    let err = machine.step_once().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::DivByZero {
+            addr: 0,
+            left: 5,
+            right: 0
+        })
+    );
+}
+
+#[test]
+fn add_wraps_at_15_bits() {
+    assert_eq!(execute(&[9, R0, 32767, 1]), 0);
+    assert_eq!(execute(&[9, R0, 32767, 32767]), 32766);
+}
+
+#[test]
+fn add_wraps_register_operands_at_15_bits() {
+    let mut machine = machine(&[1, R1, 32767, 9, R0, R1, R1]);
+    machine.step().unwrap();
+    machine.step().unwrap();
+    assert_eq!(machine.registers()[0], 32766);
+}
+
+#[test]
+fn mult_wraps_at_15_bits() {
+    assert_eq!(execute(&[10, R0, 32767, 2]), 32766);
+    assert_eq!(execute(&[10, R0, 32767, 32767]), 1);
+}
+
+#[test]
+fn mod_keeps_small_dividends() {
+    assert_eq!(execute(&[11, R0, 3, 32767]), 3);
+    assert_eq!(execute(&[11, R0, 32767, 1]), 0);
+}
+
+#[test]
+fn bitwise_results_stay_within_15_bits() {
+    assert_eq!(execute(&[12, R0, 32767, 0x4000]), 0x4000);
+    assert_eq!(execute(&[13, R0, 0x4000, 0x3fff]), 0x7fff);
+    assert_eq!(execute(&[14, R0, 0]), 0x7fff);
+    assert_eq!(execute(&[14, R0, 0x7fff]), 0);
+}
+
+#[test]
+fn gt_compares_unsigned() {
+    assert_eq!(execute(&[5, R0, 0x7fff, 0x0001]), 1);
+    assert_eq!(execute(&[5, R0, 0x0001, 0x7fff]), 0);
+    assert_eq!(execute(&[5, R0, 0x4000, 0x3fff]), 1);
+    assert_eq!(execute(&[5, R0, 0x7fff, 0x7fff]), 0);
+}
+
+#[test]
+fn eq_compares_register_values() {
+    let mut machine = machine(&[1, R1, 0x7fff, 4, R0, R1, 0x7fff, 5, R0, R1, 0x0001]);
+    machine.step().unwrap();
+    machine.step().unwrap();
+    assert_eq!(machine.registers()[0], 1);
+    machine.step().unwrap();
+    assert_eq!(machine.registers()[0], 1);
+}
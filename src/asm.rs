@@ -0,0 +1,78 @@
+use color_eyre::eyre::Context;
+
+use crate::{parse_number, OPCODES};
+
+fn parse_operand(text: &str) -> color_eyre::Result<u16> {
+    if let Some(register) = text.strip_prefix('r') {
+        let register = register
+            .parse::<u16>()
+            .ok()
+            .filter(|&register| register < 8)
+            .ok_or_else(|| color_eyre::eyre::eyre!("got weird register: {text}"))?;
+        return Ok(32768 + register);
+    }
+
+    let value = parse_number(text)?;
+    if value > 32775 {
+        return Err(color_eyre::eyre::eyre!("operand out of range: {text}"));
+    }
+    Ok(value)
+}
+
+pub fn assemble(source: &str) -> color_eyre::Result<Vec<u16>> {
+    let mut words = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or_default().trim();
+        let mut tokens = line.split_whitespace();
+        let Some(mnemonic) = tokens.next() else {
+            continue;
+        };
+
+        let parse = if mnemonic == "db" {
+            parse_number
+        } else {
+            parse_operand
+        };
+        let operands = tokens
+            .map(parse)
+            .collect::<color_eyre::Result<Vec<_>>>()
+            .wrap_err_with(|| format!("assemble line {}", number + 1))?;
+        if mnemonic == "db" {
+            words.extend(operands);
+            continue;
+        }
+
+        let (opcode, &(_, arity)) = OPCODES
+            .iter()
+            .enumerate()
+            .find(|(_, &(name, _))| name == mnemonic)
+            .ok_or_else(|| {
+                color_eyre::eyre::eyre!("unknown mnemonic on line {}: {mnemonic}", number + 1)
+            })?;
+        if operands.len() != arity {
+            return Err(color_eyre::eyre::eyre!(
+                "{mnemonic} takes {arity} operands, got {} on line {}",
+                operands.len(),
+                number + 1
+            ));
+        }
+        words.push(opcode as u16);
+        words.extend(operands);
+    }
+
+    Ok(words)
+}
+
+#[test]
+fn assembles_disassembler_listing() {
+    let source =
+        "set  r0 0xa\nout  r0\nadd  r1 r0 0x7fff\njt   r1 0x0b\nhalt\nin   r2\nwmem 0x64 r2\nnoop";
+    let words = assemble(source).unwrap();
+    assert_eq!(&words[..3], [1, 32768, 10]);
+
+    let listing = crate::disasm::listing(&words)
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect::<Vec<_>>();
+    assert_eq!(listing.join("\n"), source);
+}
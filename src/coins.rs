@@ -0,0 +1,48 @@
+const COINS: [(&str, i32); 5] = [
+    ("red", 2),
+    ("corroded", 3),
+    ("shiny", 5),
+    ("concave", 7),
+    ("blue", 9),
+];
+
+fn holds(values: [i32; 5]) -> bool {
+    let [first, second, third, fourth, fifth] = values;
+    first + second * third.pow(2) + fourth.pow(3) - fifth == 399
+}
+
+fn search(order: &mut Vec<usize>) -> bool {
+    if order.len() == COINS.len() {
+        return holds([0, 1, 2, 3, 4].map(|slot| COINS[order[slot]].1));
+    }
+
+    for coin in 0..COINS.len() {
+        if order.contains(&coin) {
+            continue;
+        }
+
+        order.push(coin);
+        if search(order) {
+            return true;
+        }
+        order.pop();
+    }
+
+    false
+}
+
+pub fn solve_coins() -> [&'static str; 5] {
+    let mut order = Vec::new();
+    assert!(
+        search(&mut order),
+        "no ordering of the coins satisfies the equation"
+    );
+
+    [0, 1, 2, 3, 4].map(|slot| COINS[order[slot]].0)
+}
+
+#[test]
+fn coin_order_satisfies_equation() {
+    let values = solve_coins().map(|name| COINS.iter().find(|&&(coin, _)| coin == name).unwrap().1);
+    assert!(holds(values));
+}
@@ -0,0 +1,617 @@
+use std::{
+    collections::VecDeque,
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use color_eyre::eyre::Context;
+
+use crate::{
+    decode_at, parse_address, report_command_error, Instruction, Location, Machine, Outcome,
+    HEADLESS_INSTRUCTION_BUDGET,
+};
+
+const UNDO_DEPTH: usize = 16;
+
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+const UNREPEATABLE: &[&str] = &[
+    "continue",
+    "c",
+    "savestate",
+    "loadstate",
+    "logfile",
+    "nolog",
+];
+
+#[derive(Debug)]
+pub struct Breakpoint {
+    pub addr: usize,
+    pub count: Option<u64>,
+    pub once: bool,
+    pub hits: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watch {
+    Memory(usize),
+    Register(usize),
+}
+
+impl std::fmt::Display for Watch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Watch::Memory(addr) => write!(f, "{addr:#06x}"),
+            Watch::Register(reg) => write!(f, "r{reg}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Snapshot {
+    mem: Vec<u16>,
+    registers: [u16; 8],
+    stack: Vec<u16>,
+    index: usize,
+    stdin: VecDeque<u8>,
+    call_depth: usize,
+    cycles: u64,
+}
+
+#[derive(Debug)]
+pub struct Debugger {
+    pub breakpoints: Vec<Breakpoint>,
+    pub watches: Vec<Watch>,
+    watch_hit: bool,
+    pub displays: Vec<Watch>,
+    pub break_depth: Option<usize>,
+    depth_reached: bool,
+    pub history_len: usize,
+    pub history: VecDeque<(usize, Instruction)>,
+    pub undo_depth: usize,
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+            watch_hit: false,
+            displays: Vec::new(),
+            break_depth: None,
+            depth_reached: false,
+            history_len: 0,
+            history: VecDeque::new(),
+            undo_depth: UNDO_DEPTH,
+            snapshots: VecDeque::new(),
+        }
+    }
+}
+
+impl Debugger {
+    pub fn record_history(&mut self, addr: usize, instruction: Instruction) {
+        if self.history_len == 0 {
+            return;
+        }
+
+        if self.history.len() == self.history_len {
+            self.history.pop_front();
+        }
+        self.history.push_back((addr, instruction));
+    }
+}
+
+impl Machine {
+    pub fn add_breakpoint(&mut self, args: &str) -> color_eyre::Result<()> {
+        let mut iter = args.split_whitespace();
+        let addr = parse_address(
+            iter.next()
+                .ok_or_else(|| color_eyre::eyre::eyre!("get breakpoint address"))?,
+        )?;
+
+        let mut breakpoint = Breakpoint {
+            addr,
+            count: None,
+            once: false,
+            hits: 0,
+        };
+        while let Some(modifier) = iter.next() {
+            match modifier {
+                "count" => {
+                    let count = iter
+                        .next()
+                        .ok_or_else(|| color_eyre::eyre::eyre!("get breakpoint count"))?
+                        .parse::<u64>()
+                        .wrap_err("parse breakpoint count into u64")?;
+                    breakpoint.count = Some(count);
+                }
+                "once" => breakpoint.once = true,
+                _ => {
+                    return Err(color_eyre::eyre::eyre!(
+                        "got weird breakpoint modifier: {modifier}"
+                    ))
+                }
+            }
+        }
+
+        self.debugger.breakpoints.push(breakpoint);
+
+        Ok(())
+    }
+
+    fn parse_watch(&self, target: &str) -> color_eyre::Result<Watch> {
+        if target.starts_with(|ch: char| ch.is_ascii_digit()) {
+            Ok(Watch::Memory(parse_address(target)?))
+        } else {
+            Ok(Watch::Register(self.parse_register(target)?))
+        }
+    }
+
+    pub fn add_watch(&mut self, args: &str) -> color_eyre::Result<()> {
+        let watch = self.parse_watch(args.trim())?;
+        self.debugger.watches.push(watch);
+
+        Ok(())
+    }
+
+    pub fn add_displays(&mut self, args: &str) -> color_eyre::Result<()> {
+        for target in args.split_whitespace() {
+            let display = self.parse_watch(target)?;
+            if !self.debugger.displays.contains(&display) {
+                self.debugger.displays.push(display);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print_displays(&self) {
+        let values = self
+            .debugger
+            .displays
+            .iter()
+            .map(|&display| {
+                let value = match display {
+                    Watch::Memory(addr) => self.mem[addr],
+                    Watch::Register(reg) => self.registers[reg],
+                };
+                format!("{display} = {value:#x}")
+            })
+            .collect::<Vec<_>>();
+        println!("{}", values.join("  "));
+    }
+
+    pub(crate) fn check_watch(&mut self, addr: usize, location: Location, old: u16, new: u16) {
+        let watch = match location {
+            Location::Address(address) => Watch::Memory(address.0),
+            Location::Register(register) => Watch::Register(register.0),
+        };
+        if !self.debugger.watches.contains(&watch) {
+            return;
+        }
+
+        println!("{watch} changed from {old:#x} to {new:#x} at {addr:#06x}");
+        self.debugger.watch_hit = true;
+    }
+
+    pub fn print_breakstats(&self) {
+        if self.debugger.breakpoints.is_empty() {
+            println!("no breakpoints");
+        }
+        for (i, breakpoint) in self.debugger.breakpoints.iter().enumerate() {
+            println!(
+                "#{i} {:#06x} hit {} times",
+                breakpoint.addr, breakpoint.hits
+            );
+        }
+    }
+
+    pub fn print_breakpoints(&self) {
+        if self.debugger.breakpoints.is_empty() {
+            println!("no breakpoints");
+        }
+        for (i, breakpoint) in self.debugger.breakpoints.iter().enumerate() {
+            let mut conditions = String::new();
+            if let Some(count) = breakpoint.count {
+                conditions.push_str(&format!(" count {count}"));
+            }
+            if breakpoint.once {
+                conditions.push_str(" once");
+            }
+            println!(
+                "#{i} {:#06x}{conditions}, hit {} times",
+                breakpoint.addr, breakpoint.hits
+            );
+        }
+    }
+
+    pub fn reset_breakpoint_hits(&mut self) {
+        for breakpoint in &mut self.debugger.breakpoints {
+            breakpoint.hits = 0;
+        }
+    }
+
+    fn hit_breakpoint(&mut self) -> bool {
+        let mut fired = false;
+        let index = self.index;
+        self.debugger.breakpoints.retain_mut(|breakpoint| {
+            if breakpoint.addr != index {
+                return true;
+            }
+
+            breakpoint.hits += 1;
+            let fires = breakpoint
+                .count
+                .is_none_or(|count| breakpoint.hits == count);
+            fired |= fires;
+            !(fires && breakpoint.once)
+        });
+
+        fired
+    }
+
+    fn hit_depth_break(&mut self) -> bool {
+        let Some(depth) = self.debugger.break_depth else {
+            return false;
+        };
+
+        if self.call_depth < depth {
+            self.debugger.depth_reached = false;
+            return false;
+        }
+        if self.debugger.depth_reached {
+            return false;
+        }
+
+        self.debugger.depth_reached = true;
+        println!(
+            "call depth reached {} at {:#06x}",
+            self.call_depth, self.index
+        );
+        true
+    }
+
+    pub fn should_pause(&mut self) -> bool {
+        self.hit_breakpoint()
+            | self.hit_depth_break()
+            | std::mem::take(&mut self.debugger.watch_hit)
+    }
+
+    pub fn print_current_instruction(&self) {
+        match decode_at(&self.mem, self.index) {
+            Ok(instruction) => println!(
+                "{:#06x}    {}",
+                self.index,
+                instruction.named(&self.register_names, &self.labels)
+            ),
+            Err(err) => println!("{:#06x}    <{err}>", self.index),
+        }
+    }
+
+    fn skip_instruction(&mut self) -> color_eyre::Result<()> {
+        let instruction = decode_at(&self.mem, self.index)?;
+        println!("warning: skipping an instruction can leave the machine in an inconsistent state");
+        self.index += instruction.width();
+
+        Ok(())
+    }
+
+    pub fn print_halt_report(&self) {
+        let Some(&(halt_addr, last)) = self.debugger.history.back() else {
+            println!("halted before executing any instructions");
+            return;
+        };
+        // `--halt-on-eof` ends the run at an `in` rather than at a real `halt`.
+        let verb = if matches!(last, Instruction::In(_)) {
+            "stopped"
+        } else {
+            "halted"
+        };
+
+        println!();
+        println!("== why-halt ==");
+        if halt_addr < self.program_len {
+            println!("{verb} at {halt_addr:#06x}, inside the loaded program image");
+        } else {
+            println!(
+                "{verb} at {halt_addr:#06x}, past the end of the loaded program image ({:#06x} words)",
+                self.program_len
+            );
+        }
+        println!("last executed instructions:");
+        self.print_history_entries(self.debugger.history.len());
+        for register in 0..self.registers.len() {
+            self.print_register(register);
+        }
+    }
+
+    fn print_history_entries(&self, count: usize) {
+        let skip = self.debugger.history.len().saturating_sub(count);
+        for (addr, instruction) in self.debugger.history.iter().skip(skip) {
+            println!(
+                "{addr:#06x}    {}",
+                instruction.named(&self.register_names, &self.labels)
+            );
+        }
+    }
+
+    pub fn print_history(&mut self, count: usize) {
+        if self.debugger.history.is_empty() {
+            println!("no instructions recorded");
+        }
+        self.print_history_entries(count);
+        if count > self.debugger.history_len {
+            self.debugger.history_len = count;
+            println!("recording the last {count} instructions from now on");
+        }
+    }
+
+    fn save_snapshot(&mut self) {
+        if self.debugger.undo_depth == 0 {
+            return;
+        }
+
+        if self.debugger.snapshots.len() == self.debugger.undo_depth {
+            self.debugger.snapshots.pop_front();
+        }
+        self.debugger.snapshots.push_back(Snapshot {
+            mem: self.mem.clone(),
+            registers: *self.registers,
+            stack: self.stack.clone(),
+            index: self.index,
+            stdin: self.stdin.clone(),
+            call_depth: self.call_depth,
+            cycles: self.cycles,
+        });
+    }
+
+    fn undo(&mut self) {
+        let Some(snapshot) = self.debugger.snapshots.pop_back() else {
+            println!("nothing to undo");
+            return;
+        };
+
+        self.mem = snapshot.mem;
+        self.clear_decoded();
+        *self.registers = snapshot.registers;
+        self.stack = snapshot.stack;
+        self.index = snapshot.index;
+        self.stdin = snapshot.stdin;
+        self.call_depth = snapshot.call_depth;
+        self.cycles = snapshot.cycles;
+        println!("restored pc {:#06x}", self.index);
+    }
+
+    fn step_instructions(&mut self, count: usize) -> color_eyre::Result<Option<Outcome>> {
+        for _ in 0..count {
+            self.save_snapshot();
+            let step = self.step_once()?;
+            println!(
+                "{:#06x}    {}",
+                step.addr,
+                step.instruction.named(&self.register_names, &self.labels)
+            );
+            if self.debugger.displays.is_empty() {
+                for register in 0..self.registers.len() {
+                    self.print_register(register);
+                }
+            }
+            if step.outcome == Outcome::Halt {
+                println!("halted");
+                return Ok(Some(Outcome::Halt));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn step_over(&mut self) -> color_eyre::Result<Option<Outcome>> {
+        let instruction = decode_at(&self.mem, self.index)?;
+        let Instruction::Call(_) = instruction else {
+            return self.step_instructions(1);
+        };
+
+        self.save_snapshot();
+        let return_addr = self.index + instruction.width();
+        let depth = self.call_depth;
+        for _ in 0..HEADLESS_INSTRUCTION_BUDGET {
+            if self.step_once()?.outcome == Outcome::Halt {
+                println!("halted");
+                return Ok(Some(Outcome::Halt));
+            }
+            if self.index == return_addr && self.call_depth == depth {
+                return Ok(None);
+            }
+            if self.should_pause() {
+                print!("stopped inside call at ");
+                return Ok(None);
+            }
+        }
+
+        Err(color_eyre::eyre::eyre!(
+            "call did not return within {HEADLESS_INSTRUCTION_BUDGET} instructions"
+        ))
+    }
+
+    fn step_out(&mut self) -> color_eyre::Result<Option<Outcome>> {
+        let depth = self.call_depth;
+        if depth == 0 {
+            println!("not inside a call");
+            return Ok(None);
+        }
+
+        self.save_snapshot();
+        for _ in 0..HEADLESS_INSTRUCTION_BUDGET {
+            let step = self.step_once()?;
+            if step.outcome == Outcome::Halt {
+                println!("halted");
+                return Ok(Some(Outcome::Halt));
+            }
+            if matches!(step.instruction, Instruction::Ret) && self.call_depth < depth {
+                print!("returned to ");
+                return Ok(None);
+            }
+            if self.should_pause() {
+                print!("stopped inside call at ");
+                return Ok(None);
+            }
+        }
+
+        Err(color_eyre::eyre::eyre!(
+            "call did not return within {HEADLESS_INSTRUCTION_BUDGET} instructions"
+        ))
+    }
+
+    pub fn invoke(&mut self, target: usize, budget: u64) -> color_eyre::Result<Outcome> {
+        let return_addr = self.index;
+        let depth = self.call_depth;
+        self.stack.push(return_addr as u16);
+        self.call_depth += 1;
+        self.index = target;
+        for _ in 0..budget {
+            if self.step_once()?.outcome == Outcome::Halt {
+                return Ok(Outcome::Halt);
+            }
+            if self.index == return_addr && self.call_depth == depth {
+                return Ok(Outcome::Continue);
+            }
+        }
+
+        Err(color_eyre::eyre::eyre!(
+            "{target:#06x} did not return within {budget} instructions"
+        ))
+    }
+
+    pub fn debug_prompt(&mut self) -> color_eyre::Result<Outcome> {
+        print!("paused at ");
+
+        loop {
+            self.print_current_instruction();
+            if !self.debugger.displays.is_empty() {
+                self.print_displays();
+            }
+            print!("dbg> ");
+            std::io::stdout().flush().wrap_err("flush stdout")?;
+
+            let line = self.read_line()?;
+            let (count, command) = line
+                .trim()
+                .split_once(' ')
+                .and_then(|(count, command)| Some((count.parse::<usize>().ok()?, command.trim())))
+                .unwrap_or((1, line.trim()));
+
+            let name = command.split_whitespace().next().unwrap_or_default();
+            if count > 1 && UNREPEATABLE.contains(&name) {
+                println!("{name} can't be repeated");
+                continue;
+            }
+
+            for _ in 0..count {
+                match self.debug_command(command) {
+                    Ok(Some(outcome)) => {
+                        INTERRUPTED.store(false, Ordering::Relaxed);
+                        return Ok(outcome);
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        report_command_error(&err);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn debug_command(&mut self, command: &str) -> color_eyre::Result<Option<Outcome>> {
+        match command {
+            "continue" | "c" => return Ok(Some(Outcome::Continue)),
+            "skip" => {
+                self.save_snapshot();
+                self.skip_instruction()?
+            }
+            "undo" => self.undo(),
+            "reset" => {
+                self.save_snapshot();
+                self.reset();
+                print!("machine reset, now at ");
+            }
+            "next" | "step-over" => return self.step_over(),
+            "finish" | "step-out" => return self.step_out(),
+            "step" => return self.step_instructions(1),
+            _ => {
+                if let Some(target) = command.strip_prefix("goto ") {
+                    self.save_snapshot();
+                    self.index = parse_address(target.trim())?;
+                    print!("jumped to ");
+                } else if let Some(args) = command.strip_prefix("run-until ") {
+                    let args = args.trim();
+                    let (needle, budget) =
+                        match args.strip_prefix('"').and_then(|args| args.split_once('"')) {
+                            Some((needle, budget)) => (needle, budget.trim()),
+                            None => (args, ""),
+                        };
+                    let budget = match budget {
+                        "" => HEADLESS_INSTRUCTION_BUDGET,
+                        budget => budget
+                            .parse::<u64>()
+                            .wrap_err("parse instruction budget into u64")?,
+                    };
+                    self.save_snapshot();
+                    if !self.run_until_output(needle, budget)? {
+                        println!("halted");
+                        return Ok(Some(Outcome::Halt));
+                    }
+                    println!();
+                    print!("found {needle:?}, paused at ");
+                } else if let Some(target) = command.strip_prefix("invoke ") {
+                    let target = parse_address(target.trim())?;
+                    self.save_snapshot();
+                    if self.invoke(target, HEADLESS_INSTRUCTION_BUDGET)? == Outcome::Halt {
+                        println!("halted");
+                        return Ok(Some(Outcome::Halt));
+                    }
+                    for register in 0..self.registers.len() {
+                        self.print_register(register);
+                    }
+                    print!("returned to ");
+                } else if let Some(path) = command.strip_prefix("load-program ") {
+                    let path = path.trim();
+                    let program = std::fs::read(path).wrap_err("read program")?;
+                    self.load_program(&program)?;
+                    self.debugger.snapshots.clear();
+                    print!("loaded {} words, now at ", self.program_len);
+                } else if let Some(depth) = command.strip_prefix("undo-depth ") {
+                    self.debugger.undo_depth = depth
+                        .trim()
+                        .parse::<usize>()
+                        .wrap_err("parse undo depth into usize")?;
+                    while self.debugger.snapshots.len() > self.debugger.undo_depth {
+                        self.debugger.snapshots.pop_front();
+                    }
+                } else if let Some(count) = command.strip_prefix("step ") {
+                    let count = count
+                        .trim()
+                        .parse::<usize>()
+                        .wrap_err("parse step count into usize")?;
+                    return self.step_instructions(count);
+                } else if !self.run_command(command)? {
+                    println!("unknown debugger command: {command}");
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[test]
+fn step_over_runs_nested_calls_to_completion() {
+    let mut machine =
+        crate::test_util::machine(&[17, 4, 19, 'c' as u16, 17, 7, 18, 19, 'b' as u16, 18]);
+    machine.output = crate::Output::Capture(Vec::new());
+    assert!(machine.step_over().unwrap().is_none());
+    assert_eq!(machine.index, 2);
+    assert_eq!(machine.call_depth, 0);
+    assert_eq!(machine.take_output(), b"b");
+}
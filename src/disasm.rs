@@ -0,0 +1,162 @@
+use crate::{
+    decode_at, instructions,
+    labels::{LabelMap, NO_LABELS},
+    Instruction, Instructions, Machine, Operand, VmError, NO_NAMES,
+};
+
+fn render(
+    mem: &[u16],
+    labels: &LabelMap,
+    color: bool,
+    instructions: impl Iterator<Item = (usize, Result<Instruction, VmError>)>,
+) -> Vec<(usize, String)> {
+    instructions
+        .map(|(addr, decoded)| match decoded {
+            Ok(instruction) => (
+                addr,
+                instruction
+                    .named(&NO_NAMES, labels)
+                    .colored(color)
+                    .to_string(),
+            ),
+            Err(_) => (addr, format!("db   {:#06x}", mem[addr])),
+        })
+        .collect()
+}
+
+pub fn listing(mem: &[u16]) -> Vec<(usize, String)> {
+    render(mem, &NO_LABELS, false, instructions(mem))
+}
+
+impl Machine {
+    pub fn disassemble_reachable(&self) -> Vec<(usize, String)> {
+        let reachable = self.reachable_code();
+        let program = self.program();
+        let mut listing = Vec::new();
+        let mut addr = 0;
+        while addr < program.len() {
+            match decode_at(program, addr) {
+                Ok(instruction) if reachable.contains(&addr) => {
+                    listing.push((
+                        addr,
+                        instruction
+                            .named(&NO_NAMES, &self.labels)
+                            .colored(self.color)
+                            .to_string(),
+                    ));
+                    addr += instruction.width();
+                }
+                _ => {
+                    listing.push((addr, format!("db   {:#06x}", program[addr])));
+                    addr += 1;
+                }
+            }
+        }
+
+        listing
+    }
+
+    pub fn disassemble_range(&self, start: usize, len: usize) -> Vec<(usize, String)> {
+        let instructions = Instructions {
+            mem: &self.mem,
+            addr: start,
+        };
+        render(
+            &self.mem,
+            &self.labels,
+            self.color,
+            instructions.take_while(|&(addr, _)| addr < start + len),
+        )
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperandEntry {
+    Register(usize),
+    Literal(u16),
+}
+
+impl From<Operand> for OperandEntry {
+    fn from(operand: Operand) -> Self {
+        match operand.register() {
+            Some(register) => OperandEntry::Register(register.0),
+            None => OperandEntry::Literal(operand.encode()),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct TraceEntry {
+    addr: usize,
+    mnemonic: &'static str,
+    operands: Vec<OperandEntry>,
+    registers: [u16; 8],
+}
+
+impl TraceEntry {
+    pub fn new(addr: usize, instruction: &Instruction, registers: [u16; 8]) -> Self {
+        Self {
+            addr,
+            mnemonic: instruction.mnemonic(),
+            operands: instruction
+                .operands()
+                .into_iter()
+                .map(OperandEntry::from)
+                .collect(),
+            registers,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(untagged)]
+pub enum ProgramEntry {
+    Instruction {
+        addr: usize,
+        opcode: u16,
+        mnemonic: &'static str,
+        operands: Vec<OperandEntry>,
+    },
+    Data {
+        addr: usize,
+        raw: u16,
+    },
+}
+
+pub fn program_entries(mem: &[u16]) -> Vec<ProgramEntry> {
+    instructions(mem)
+        .map(|(addr, decoded)| match decoded {
+            Ok(instruction) => ProgramEntry::Instruction {
+                addr,
+                opcode: instruction.opcode(),
+                mnemonic: instruction.mnemonic(),
+                operands: instruction
+                    .operands()
+                    .into_iter()
+                    .map(OperandEntry::from)
+                    .collect(),
+            },
+            Err(_) => ProgramEntry::Data {
+                addr,
+                raw: mem[addr],
+            },
+        })
+        .collect()
+}
+
+#[test]
+fn disassembles_confirmation_routine() {
+    let program = std::fs::read("challenge.bin").unwrap();
+    let machine = Machine::new(&program);
+
+    let listing = machine.disassemble_range(0x178b, 8);
+    assert_eq!(
+        listing,
+        [
+            (0x178b, "jt   r0 0x1793".to_string()),
+            (0x178e, "add  r0 r1 0x1".to_string()),
+            (0x1792, "ret ".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,283 @@
+use std::io::Cursor;
+
+use crate::{decode_at, test_util::machine, Input, Location, Machine, Output, Value, VmError};
+
+#[test]
+fn ret_on_empty_stack_reports_pc() {
+    let err = machine(&[18]).step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::StackUnderflow {
+            addr: 0,
+            mnemonic: "ret"
+        })
+    );
+    assert_eq!(err.to_string(), "stack underflow at 0x0000 (ret)");
+}
+
+#[test]
+fn rmem_out_of_bounds_reports_pc() {
+    let mut machine = machine(&[21, 15, 32769, 32768]);
+    machine.registers[0] = 0x8000;
+    machine.step().unwrap();
+
+    let err = machine.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::OutOfBounds {
+            pc: 1,
+            addr: 0x8000
+        })
+    );
+}
+
+#[test]
+fn wmem_out_of_bounds_reports_pc() {
+    let mut machine = machine(&[16, 32768, 7]);
+    machine.registers[0] = 0xffff;
+
+    let err = machine.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::OutOfBounds {
+            pc: 0,
+            addr: 0xffff
+        })
+    );
+}
+
+fn decode_error(words: &[u16]) -> VmError {
+    decode_at(words, 0).unwrap_err()
+}
+
+#[test]
+fn set_with_literal_register_names_operand() {
+    assert_eq!(
+        decode_error(&[1, 5, 7]),
+        VmError::BadOperand {
+            addr: 0,
+            mnemonic: "set",
+            operand: 1,
+            value: 5
+        }
+    );
+}
+
+#[test]
+fn value_past_registers_names_operand() {
+    assert_eq!(
+        decode_error(&[9, 32768, 1, 32776]),
+        VmError::BadOperand {
+            addr: 0,
+            mnemonic: "add",
+            operand: 3,
+            value: 32776
+        }
+    );
+}
+
+#[test]
+fn location_past_registers_names_operand() {
+    assert_eq!(
+        decode_error(&[15, 32768, 40000]),
+        VmError::BadOperand {
+            addr: 0,
+            mnemonic: "rmem",
+            operand: 2,
+            value: 40000
+        }
+    );
+}
+
+#[test]
+fn operands_past_registers_are_bad_values() {
+    assert_eq!(
+        Value::new(32776).unwrap_err(),
+        VmError::BadValue { value: 32776 }
+    );
+    assert_eq!(
+        Location::new(0xffff).unwrap_err(),
+        VmError::BadValue { value: 0xffff }
+    );
+}
+
+#[test]
+fn truncated_instruction_is_out_of_bounds() {
+    assert_eq!(
+        decode_error(&[4, 32768]),
+        VmError::OutOfBounds { pc: 0, addr: 2 }
+    );
+}
+
+#[test]
+fn infinite_loop_exceeds_instruction_budget() {
+    let mut machine = machine(&[6, 0]);
+    machine.max_instructions = Some(100);
+    let err = machine.run().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "instruction budget of 100 exceeded at 0x0000"
+    );
+}
+
+#[test]
+fn instruction_budget_counts_from_a_restored_state() {
+    let mut machine = machine(&[6, 0]);
+    machine.cycles = 701_401;
+    let state = postcard::to_stdvec(&machine).unwrap();
+
+    let mut restored = Machine::from_state_bytes(&state).unwrap();
+    restored.max_instructions = Some(100);
+    let err = restored.run().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "instruction budget of 100 exceeded at 0x0000"
+    );
+    assert_eq!(restored.cycles(), 701_501);
+}
+
+#[test]
+fn malformed_state_fails_to_load() {
+    let mut state = serde_json::to_value(machine(&[0])).unwrap();
+    state["mem"] = serde_json::json!([0, 0, 0]);
+    let err = Machine::from_state_bytes(state.to_string().as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "got weird memory size in state: 3");
+
+    let mut state = serde_json::to_value(machine(&[0])).unwrap();
+    state["registers"] = serde_json::json!([0, 0, 0, 0, 0, 0, 0]);
+    let err = machine(&[0])
+        .load_state(state.to_string().as_bytes())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "deserialize state");
+}
+
+#[test]
+fn jmp_to_large_register_value_is_out_of_bounds() {
+    let mut machine = machine(&[6, 32768]);
+    machine.registers[0] = 0xffff;
+    let err = machine.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::OutOfBounds {
+            pc: 0,
+            addr: 0xffff
+        })
+    );
+}
+
+#[test]
+fn ret_to_address_past_15_bits_is_an_error() {
+    let mut machine = machine(&[18]);
+    machine.stack.push(0x8000);
+    let err = machine.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::PcOutOfRange {
+            addr: 0,
+            pc: 0x8000
+        })
+    );
+}
+
+#[test]
+fn call_at_end_of_memory_reports_return_address() {
+    let mut machine = machine(&[]);
+    machine.mem[0x7ffe] = 17;
+    machine.index = 0x7ffe;
+    let err = machine.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::PcOutOfRange {
+            addr: 0x7ffe,
+            pc: 0x8000
+        })
+    );
+}
+
+#[test]
+fn bad_command_arguments_keep_the_machine_running() {
+    for command in ["dumpreg 8\n", "setreg 100 5\n", "setreg 7 0x8000\n"] {
+        let mut machine = machine(&[20, 32768, 19, 32768, 0]);
+        let input = format!("{command}setreg 7 9\nx\n");
+        machine.input = Input::Reader(Box::new(Cursor::new(input.into_bytes())));
+        machine.output = Output::Capture(Vec::new());
+        machine.run().unwrap();
+        assert_eq!(machine.registers()[7], 9);
+        assert_eq!(machine.take_output(), b"x");
+    }
+}
+
+#[test]
+fn bad_debugger_commands_keep_the_prompt_open() {
+    let mut machine = machine(&[19, 'a' as u16, 19, 'b' as u16, 0]);
+    machine.add_breakpoint("0x2").unwrap();
+    machine.input = Input::Reader(Box::new(Cursor::new(
+        b"goto 0x9000\nsetreg 9 1\ncontinue\n".to_vec(),
+    )));
+    machine.output = Output::Capture(Vec::new());
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), b"ab");
+}
+
+#[test]
+fn load_program_rejects_odd_lengths() {
+    let mut machine = machine(&[0]);
+    let err = machine.load_program(&[19, 0, 65]).unwrap_err();
+    assert_eq!(err.to_string(), "program has an odd number of bytes: 3");
+
+    machine.load_program(&[19, 0, 65, 0, 0, 0]).unwrap();
+    assert_eq!(machine.program(), [19, 65, 0]);
+}
+
+#[test]
+fn oversized_program_is_truncated_to_memory() {
+    let mut machine = machine(&vec![21; crate::MEM_SIZE + 4]);
+    assert_eq!(machine.program().len(), crate::MEM_SIZE);
+
+    machine
+        .load_program(&[0; (crate::MEM_SIZE + 4) * 2])
+        .unwrap();
+    assert_eq!(machine.program().len(), crate::MEM_SIZE);
+}
+
+#[test]
+fn strict_mode_allows_register_encodings_in_rmem() {
+    let mut machine = machine(&[15, 32768, 1]);
+    machine.strict = true;
+    machine.step().unwrap();
+    assert_eq!(machine.registers()[0], 32768);
+}
+
+#[test]
+fn strict_mode_rejects_out_of_range_pushes() {
+    let mut machine = machine(&[15, 32768, 5, 2, 32768, 0x9000]);
+    machine.step().unwrap();
+    machine.step().unwrap();
+    assert_eq!(machine.stack, [0x9000]);
+
+    let mut machine = self::machine(&[15, 32768, 5, 2, 32768, 0x9000]);
+    machine.strict = true;
+    machine.step().unwrap();
+    let err = machine.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::ValueOutOfRange {
+            addr: 3,
+            value: 0x9000
+        })
+    );
+}
+
+#[test]
+fn out_of_range_register_operand_reports_pc() {
+    let mut machine = machine(&[15, 32768, 5, 19, 32768, 0x9000]);
+    machine.step().unwrap();
+    let err = machine.step().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<VmError>(),
+        Some(&VmError::ValueOutOfRange {
+            addr: 3,
+            value: 0x9000
+        })
+    );
+}
@@ -15,8 +15,7 @@ const GRID: [[Square; 4]; 4] = [
     [Square::Num(22), Square::Sub, Square::Num(9), Square::Mult],
 ];
 
-#[test]
-fn traverse_grid() {
+pub fn solve_vault() -> Vec<&'static str> {
     let mut visited = HashSet::new();
     let mut queue = VecDeque::from([(0, 3, 0, Some(Square::Add), Vec::new())]);
     while let Some((x, y, weight, op, path)) = queue.pop_front() {
@@ -38,8 +37,7 @@ fn traverse_grid() {
 
         if x == 3 && y == 0 {
             if new_weight == 30 {
-                println!("the path is: {path:?}");
-                return;
+                return path;
             } else {
                 continue;
             }
@@ -47,26 +45,54 @@ fn traverse_grid() {
 
         if x > 0 && y != 3 && !visited.contains(&(x - 1, y, new_weight, new_op)) {
             let mut new_path = path.clone();
-            new_path.push("left");
+            new_path.push("west");
             queue.push_back((x - 1, y, new_weight, new_op, new_path));
         }
 
         if x < 3 && !visited.contains(&(x + 1, y, new_weight, new_op)) {
             let mut new_path = path.clone();
-            new_path.push("right");
+            new_path.push("east");
             queue.push_back((x + 1, y, new_weight, new_op, new_path));
         }
 
         if y > 0 && !visited.contains(&(x, y - 1, new_weight, new_op)) {
             let mut new_path = path.clone();
-            new_path.push("up");
+            new_path.push("north");
             queue.push_back((x, y - 1, new_weight, new_op, new_path));
         }
 
         if y < 3 && x != 0 && !visited.contains(&(x, y + 1, new_weight, new_op)) {
             let mut new_path = path;
-            new_path.push("down");
+            new_path.push("south");
             queue.push_back((x, y + 1, new_weight, new_op, new_path));
         }
     }
+
+    Vec::new()
+}
+
+#[test]
+fn vault_path_weighs_30() {
+    let path = solve_vault();
+    let (mut x, mut y) = (0usize, 3usize);
+    let mut weight = 22;
+    let mut op = None;
+    for direction in path {
+        match direction {
+            "north" => y -= 1,
+            "south" => y += 1,
+            "east" => x += 1,
+            "west" => x -= 1,
+            _ => unreachable!(),
+        }
+        match (GRID[y][x], op) {
+            (Square::Num(num), Some(Square::Add)) => weight += num,
+            (Square::Num(num), Some(Square::Sub)) => weight -= num,
+            (Square::Num(num), Some(Square::Mult)) => weight *= num,
+            (square, _) => op = Some(square),
+        }
+    }
+
+    assert_eq!((x, y), (3, 0));
+    assert_eq!(weight, 30);
 }
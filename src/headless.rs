@@ -0,0 +1,364 @@
+use std::{
+    cell::RefCell,
+    io::{BufReader, Cursor, Read, Write},
+    rc::Rc,
+};
+
+use crate::{
+    test_util::{machine, program},
+    Input, JsonTrace, Machine, Outcome, Output, StepOutcome,
+};
+
+#[test]
+fn echoes_through_headless_io() {
+    let program = program(&[20, 32768, 19, 32768, 20, 32768, 19, 32768, 0]);
+
+    let output = Machine::run_headless(&program, "hi").unwrap();
+    assert_eq!(output, "hi");
+}
+
+#[test]
+fn challenge_reaches_foothills() {
+    let program = std::fs::read("challenge.bin").unwrap();
+
+    let output = Machine::run_headless(&program, "look\n").unwrap();
+    assert!(output.contains("== Foothills =="));
+}
+
+#[test]
+fn step_executes_one_instruction() {
+    let mut machine = machine(&[19, 'a' as u16, 19, 'b' as u16, 0]);
+    machine.output = Output::Capture(Vec::new());
+    assert_eq!(machine.step().unwrap(), Outcome::Continue);
+    assert_eq!(machine.cycles(), 1);
+    assert_eq!(machine.step().unwrap(), Outcome::Continue);
+    assert_eq!(machine.step().unwrap(), Outcome::Halt);
+
+    let Output::Capture(output) = &machine.output else {
+        unreachable!();
+    };
+    assert_eq!(output, b"ab");
+}
+
+#[test]
+fn breakpoint_pauses_until_continue() {
+    let mut machine = machine(&[19, 'a' as u16, 19, 'b' as u16, 0]);
+    machine.add_breakpoint("0x2").unwrap();
+    machine.input = Input::Reader(Box::new(Cursor::new(b"continue\n".to_vec())));
+    machine.output = Output::Capture(Vec::new());
+    machine.run().unwrap();
+
+    let Output::Capture(output) = &machine.output else {
+        unreachable!();
+    };
+    assert_eq!(output, b"ab");
+    assert_eq!(machine.debugger.breakpoints[0].hits, 1);
+}
+
+#[test]
+fn exposes_registers_and_stack() {
+    let mut machine = machine(&[1, 32770, 7, 2, 32770, 2, 9, 0]);
+    machine.step().unwrap();
+    machine.step().unwrap();
+    machine.step().unwrap();
+
+    assert_eq!(machine.registers(), [0, 0, 7, 0, 0, 0, 0, 0]);
+    assert_eq!(machine.stack(), [7, 9]);
+}
+
+#[test]
+fn restores_binary_and_json_states() {
+    let mut machine = machine(&[1, 32775, 0x1234, 0]);
+    machine.step().unwrap();
+
+    let binary = postcard::to_stdvec(&machine).unwrap();
+    let json = serde_json::to_vec(&machine).unwrap();
+    for state in [binary, json] {
+        let mut restored = Machine::from_state_bytes(&state).unwrap();
+        assert_eq!(restored.registers(), machine.registers());
+        assert_eq!(restored.index, 3);
+        assert_eq!(restored.program(), machine.program());
+        assert_eq!(restored.state_dir, machine.state_dir);
+
+        assert!(restored.dispatch_command("setreg 0 5"));
+        assert_eq!(restored.registers()[0], 5);
+    }
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn writes_raw_bytes_to_configured_writer() {
+    let buffer = SharedBuffer::default();
+    let mut machine = machine(&[19, 0xff, 19, 'a' as u16, 19, '\n' as u16, 19, 'b' as u16, 0]);
+    machine.output = Output::Writer(Box::new(buffer.clone()));
+    machine.run().unwrap();
+
+    assert_eq!(*buffer.0.borrow(), b"\xffa\nb");
+}
+
+#[test]
+fn runs_in_band_commands_from_reader() {
+    let mut machine = machine(&[20, 32768, 0]);
+    machine.input = Input::Reader(Box::new(Cursor::new(b"setreg 7 9\nx\n".to_vec())));
+    machine.output = Output::Capture(Vec::new());
+    machine.run().unwrap();
+
+    assert_eq!(machine.registers()[7], 9);
+}
+
+#[test]
+fn steps_without_blocking_on_input() {
+    let mut machine = machine(&[20, 32768, 19, 32768, 0]);
+    machine.output = Output::Capture(Vec::new());
+    assert_eq!(machine.step_nonblocking().unwrap(), StepOutcome::NeedInput);
+    assert_eq!(machine.step_nonblocking().unwrap(), StepOutcome::NeedInput);
+
+    machine.feed_input(b"x");
+    assert_eq!(machine.step_nonblocking().unwrap(), StepOutcome::Continue);
+    assert_eq!(machine.step_nonblocking().unwrap(), StepOutcome::Continue);
+    assert_eq!(machine.take_output(), b"x");
+    assert_eq!(machine.step_nonblocking().unwrap(), StepOutcome::Halt);
+}
+
+#[test]
+fn decode_cache_sees_self_modifying_writes() {
+    let mut machine = machine(&[
+        19, 'a' as u16, 16, 1, 'b' as u16, 8, 32769, 9, 0, 1, 32769, 1, 6, 0,
+    ]);
+    machine.output = Output::Capture(Vec::new());
+    machine.enable_decode_cache();
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), b"ab");
+}
+
+#[test]
+fn reset_restores_the_loaded_program() {
+    let program = program(&[16, 4, 'b' as u16, 19, 'a' as u16, 0]);
+
+    let mut machine = Machine::new(&program);
+    machine.output = Output::Capture(Vec::new());
+    machine.run().unwrap();
+    assert_eq!(machine.changed_words(), [(4, 'a' as u16, 'b' as u16)]);
+    machine.reset();
+    assert_eq!(machine.cycles(), 0);
+    assert_eq!(machine.program()[4], 'a' as u16);
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), b"bb");
+}
+
+#[test]
+fn redo_injects_only_the_configured_command() {
+    let program = program(&[20, 32768, 19, 32768, 0]);
+
+    let mut machine = Machine::new(&program);
+    machine.input = Input::Reader(Box::new(Cursor::new(b"setreg 7 9\nx\n".to_vec())));
+    machine.output = Output::Capture(Vec::new());
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), b"x");
+
+    let mut machine = Machine::new(&program);
+    machine.input = Input::Reader(Box::new(Cursor::new(b"setreg 7 9\nx\n".to_vec())));
+    machine.output = Output::Capture(Vec::new());
+    machine.eof_redo_command = Some("look".to_string());
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), b"l");
+}
+
+#[test]
+fn echoes_game_input_but_not_commands() {
+    let mut machine = machine(&[20, 32768, 19, 32768, 0]);
+    machine.input = Input::Reader(Box::new(Cursor::new(b"setreg 7 9\nx\n".to_vec())));
+    machine.output = Output::Capture(Vec::new());
+    machine.input_echo = true;
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), b"x\nx");
+}
+
+#[test]
+fn image_bytes_round_trip_through_new() {
+    let program = program(&[16, 6, 21, 19, 'a' as u16, 0, 0, 0]);
+
+    let mut machine = Machine::new(&program);
+    machine.output = Output::Capture(Vec::new());
+    machine.step().unwrap();
+    let image = machine.image_bytes(None);
+    assert_eq!(image.len(), 14);
+
+    let patched = Machine::new(&image);
+    assert_eq!(patched.program(), [16, 6, 21, 19, 'a' as u16, 0, 21]);
+}
+
+#[test]
+fn runs_until_output_appears() {
+    let program = program(&[19, 'o' as u16, 19, 'k' as u16, 19, '!' as u16, 0]);
+
+    let mut machine = Machine::new(&program);
+    machine.output = Output::Capture(Vec::new());
+    assert!(machine.run_until_output("ok", 10).unwrap());
+    assert_eq!(machine.take_output(), b"ok");
+    assert!(!machine.run_until_output("ok", 10).unwrap());
+    assert!(Machine::new(&program).run_until_output("ok", 1).is_err());
+}
+
+#[test]
+fn invoke_runs_a_subroutine_and_returns() {
+    let mut machine = machine(&[0, 9, 32768, 32768, 5, 18]);
+    assert_eq!(machine.invoke(1, 100).unwrap(), Outcome::Continue);
+    assert_eq!(machine.registers()[0], 5);
+    assert!(machine.stack().is_empty());
+    assert_eq!(machine.step().unwrap(), Outcome::Halt);
+    assert!(machine
+        .invoke(0, 100)
+        .is_ok_and(|outcome| outcome == Outcome::Halt));
+}
+
+#[test]
+fn raw_input_translates_escapes() {
+    let mut machine = machine(&[
+        20, 32768, 19, 32768, 20, 32768, 19, 32768, 20, 32768, 19, 32768, 0,
+    ]);
+    machine.input = Input::Reader(Box::new(Cursor::new(b"\\x1b\\t\n".to_vec())));
+    machine.output = Output::Capture(Vec::new());
+    machine.raw_input = true;
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), b"\x1b\t\n");
+}
+
+#[test]
+fn raw_input_skips_lines_with_bad_escapes() {
+    let mut machine = machine(&[20, 32768, 19, 32768, 0]);
+    machine.input = Input::Reader(Box::new(Cursor::new(b"take \\q\nx\n".to_vec())));
+    machine.output = Output::Capture(Vec::new());
+    machine.raw_input = true;
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), b"x");
+}
+
+#[test]
+fn writes_json_trace_lines() {
+    let buffer = SharedBuffer::default();
+    let mut machine = machine(&[9, 32768, 2, 3, 0]);
+    machine.trace_json = Some(JsonTrace(Box::new(buffer.clone())));
+    machine.run().unwrap();
+
+    let trace = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        r#"{"addr":0,"mnemonic":"add","operands":[{"register":0},{"literal":2},{"literal":3}],"registers":[5,0,0,0,0,0,0,0]}"#
+    );
+    assert_eq!(lines.len(), 2);
+}
+
+struct PromptCheck {
+    output: SharedBuffer,
+    seen: Rc<RefCell<Vec<u8>>>,
+    input: &'static [u8],
+}
+
+impl Read for PromptCheck {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        *self.seen.borrow_mut() = self.output.0.borrow().clone();
+        self.input.read(buf)
+    }
+}
+
+#[test]
+fn flushes_prompt_before_blocking_on_input() {
+    let output = SharedBuffer::default();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut machine = machine(&[19, '>' as u16, 20, 32768, 0]);
+    machine.output = Output::Writer(Box::new(output.clone()));
+    machine.input = Input::Reader(Box::new(BufReader::new(PromptCheck {
+        output,
+        seen: seen.clone(),
+        input: b"x\n",
+    })));
+    machine.run().unwrap();
+
+    assert_eq!(*seen.borrow(), b">");
+}
+
+#[test]
+fn loading_a_state_saved_outside_in_does_not_rewind() {
+    let program = program(&[19, 'A' as u16, 19, 'B' as u16, 0]);
+    let mut machine = Machine::new(&program);
+    machine.output = Output::Capture(Vec::new());
+    let at_start = postcard::to_stdvec(&machine).unwrap();
+    machine.step().unwrap();
+    let after_out = postcard::to_stdvec(&machine).unwrap();
+
+    for (state, expected) in [(at_start, &b"AB"[..]), (after_out, b"B")] {
+        let mut restored = Machine::new(&program);
+        restored.output = Output::Capture(Vec::new());
+        restored.load_state(&state).unwrap();
+        restored.redo_stdin();
+        restored.run().unwrap();
+        assert_eq!(restored.take_output(), expected);
+    }
+}
+
+#[test]
+fn loading_a_state_saved_mid_in_reruns_the_in() {
+    let program = program(&[20, 32768, 19, 32768, 0]);
+    let mut machine = Machine::new(&program);
+    machine.input = Input::Reader(Box::new(Cursor::new(b"setreg 7 9\n".to_vec())));
+    machine.halt_on_eof = true;
+    machine.run().unwrap();
+    let mid_in = postcard::to_stdvec(&machine).unwrap();
+
+    let mut restored = Machine::new(&program);
+    restored.output = Output::Capture(Vec::new());
+    restored.load_state(&mid_in).unwrap();
+    restored.redo_stdin();
+    restored.feed_input(b"y\n");
+    restored.run().unwrap();
+    assert_eq!(restored.take_output(), b"y");
+}
+
+#[test]
+fn queued_loadstate_keeps_the_rest_of_the_script() {
+    let program = program(&[20, 32768, 19, 32768, 6, 0]);
+    let mut machine = Machine::new(&program);
+    machine.input = Input::Reader(Box::new(Cursor::new(b"q\n".to_vec())));
+    machine.output = Output::Capture(Vec::new());
+    machine.halt_on_eof = true;
+    machine.run().unwrap();
+    let path = std::env::temp_dir().join(format!("synacor-queued-{}.bin", std::process::id()));
+    std::fs::write(&path, postcard::to_stdvec(&machine).unwrap()).unwrap();
+
+    let mut restored = Machine::new(&program);
+    restored.input = Input::Reader(Box::new(Cursor::new(Vec::new())));
+    restored.output = Output::Capture(Vec::new());
+    restored.halt_on_eof = true;
+    restored.feed_input(format!("loadstate {}\nzz\n", path.display()).as_bytes());
+    restored.run().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(restored.take_output(), b"zz\n");
+}
+
+#[test]
+fn restored_state_dispatches_queued_commands() {
+    let mut machine = machine(&[20, 32768, 19, 32768, 0]);
+    machine.stdin.extend(b"setreg 7 9\nx\n");
+    let state = postcard::to_stdvec(&machine).unwrap();
+
+    let mut restored = Machine::from_state_bytes(&state).unwrap();
+    restored.output = Output::Capture(Vec::new());
+    restored.run().unwrap();
+    assert_eq!(restored.registers()[7], 9);
+    assert_eq!(restored.take_output(), b"x");
+}
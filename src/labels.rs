@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::Context;
+
+use crate::parse_address;
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct LabelMap(BTreeMap<usize, String>);
+
+pub static NO_LABELS: LabelMap = LabelMap(BTreeMap::new());
+
+impl LabelMap {
+    pub fn parse(text: &str) -> color_eyre::Result<Self> {
+        let mut labels = Self::default();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (addr, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| color_eyre::eyre::eyre!("got weird label line: {line}"))?;
+            labels.insert(parse_address(addr)?, name.trim())?;
+        }
+
+        Ok(labels)
+    }
+
+    pub fn load(&mut self, text: &str) -> color_eyre::Result<()> {
+        let labels = Self::parse(text).wrap_err("parse labels")?;
+        self.0.extend(labels.0);
+
+        Ok(())
+    }
+
+    pub fn insert(&mut self, addr: usize, name: &str) -> color_eyre::Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(color_eyre::eyre::eyre!("got weird label name: {name:?}"));
+        }
+
+        self.0.insert(addr, name.to_string());
+        Ok(())
+    }
+
+    pub fn get(&self, addr: usize) -> Option<&str> {
+        self.0.get(&addr).map(String::as_str)
+    }
+}
+
+#[test]
+fn parses_label_file() {
+    let labels =
+        LabelMap::parse("# routines\n0x178b teleporter_check\n\n1458 print  # strings\n").unwrap();
+    assert_eq!(labels.get(0x178b), Some("teleporter_check"));
+    assert_eq!(labels.get(1458), Some("print"));
+    assert_eq!(labels.get(0), None);
+}
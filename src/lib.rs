@@ -0,0 +1,2215 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufRead, Cursor, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+
+use color_eyre::eyre::{Context, ContextCompat};
+use debugger::Debugger;
+use labels::{LabelMap, NO_LABELS};
+use map::GameMap;
+use owo_colors::OwoColorize;
+use stats::{CallGraph, RunStats};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Instruction {
+    Halt,
+    Set(Register, Value),
+    Push(Value),
+    Pop(Location),
+    Eq(Location, Value, Value),
+    Gt(Location, Value, Value),
+    Jmp(Location),
+    Jt(Value, Location),
+    Jf(Value, Location),
+    Add(Location, Value, Value),
+    Mult(Location, Value, Value),
+    Mod(Location, Value, Value),
+    And(Location, Value, Value),
+    Or(Location, Value, Value),
+    Not(Location, Value),
+    Rmem(Location, Location),
+    Wmem(Location, Value),
+    Call(Location),
+    Ret,
+    Out(Value),
+    In(Location),
+    Noop,
+}
+
+const OPCODES: [(&str, usize); 22] = [
+    ("halt", 0),
+    ("set", 2),
+    ("push", 1),
+    ("pop", 1),
+    ("eq", 3),
+    ("gt", 3),
+    ("jmp", 1),
+    ("jt", 2),
+    ("jf", 2),
+    ("add", 3),
+    ("mult", 3),
+    ("mod", 3),
+    ("and", 3),
+    ("or", 3),
+    ("not", 2),
+    ("rmem", 2),
+    ("wmem", 2),
+    ("call", 1),
+    ("ret", 0),
+    ("out", 1),
+    ("in", 1),
+    ("noop", 0),
+];
+
+impl Instruction {
+    pub fn opcode(&self) -> u16 {
+        match self {
+            Instruction::Halt => 0,
+            Instruction::Set(..) => 1,
+            Instruction::Push(..) => 2,
+            Instruction::Pop(..) => 3,
+            Instruction::Eq(..) => 4,
+            Instruction::Gt(..) => 5,
+            Instruction::Jmp(..) => 6,
+            Instruction::Jt(..) => 7,
+            Instruction::Jf(..) => 8,
+            Instruction::Add(..) => 9,
+            Instruction::Mult(..) => 10,
+            Instruction::Mod(..) => 11,
+            Instruction::And(..) => 12,
+            Instruction::Or(..) => 13,
+            Instruction::Not(..) => 14,
+            Instruction::Rmem(..) => 15,
+            Instruction::Wmem(..) => 16,
+            Instruction::Call(..) => 17,
+            Instruction::Ret => 18,
+            Instruction::Out(..) => 19,
+            Instruction::In(..) => 20,
+            Instruction::Noop => 21,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        OPCODES[self.opcode() as usize].0
+    }
+
+    pub fn width(&self) -> usize {
+        OPCODES[self.opcode() as usize].1 + 1
+    }
+
+    pub fn operands(&self) -> Vec<Operand> {
+        match *self {
+            Instruction::Halt | Instruction::Ret | Instruction::Noop => vec![],
+            Instruction::Set(register, value) => {
+                vec![Operand::Register(register), Operand::Value(value)]
+            }
+            Instruction::Push(value) | Instruction::Out(value) => vec![Operand::Value(value)],
+            Instruction::Pop(location)
+            | Instruction::Jmp(location)
+            | Instruction::Call(location)
+            | Instruction::In(location) => vec![Operand::Location(location)],
+            Instruction::Eq(location, left, right)
+            | Instruction::Gt(location, left, right)
+            | Instruction::Add(location, left, right)
+            | Instruction::Mult(location, left, right)
+            | Instruction::Mod(location, left, right)
+            | Instruction::And(location, left, right)
+            | Instruction::Or(location, left, right) => vec![
+                Operand::Location(location),
+                Operand::Value(left),
+                Operand::Value(right),
+            ],
+            Instruction::Jt(value, location) | Instruction::Jf(value, location) => {
+                vec![Operand::Value(value), Operand::Location(location)]
+            }
+            Instruction::Not(location, value) | Instruction::Wmem(location, value) => {
+                vec![Operand::Location(location), Operand::Value(value)]
+            }
+            Instruction::Rmem(dest, src) => {
+                vec![Operand::Location(dest), Operand::Location(src)]
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.named(&NO_NAMES, &NO_LABELS))
+    }
+}
+
+type RegisterNames = [Option<String>; 8];
+
+static NO_NAMES: RegisterNames = [const { None }; 8];
+
+struct NamedInstruction<'a> {
+    instruction: &'a Instruction,
+    names: &'a RegisterNames,
+    labels: &'a LabelMap,
+    color: bool,
+}
+
+impl Instruction {
+    fn named<'a>(&'a self, names: &'a RegisterNames, labels: &'a LabelMap) -> NamedInstruction<'a> {
+        NamedInstruction {
+            instruction: self,
+            names,
+            labels,
+            color: false,
+        }
+    }
+}
+
+impl NamedInstruction<'_> {
+    fn colored(self, color: bool) -> Self {
+        Self { color, ..self }
+    }
+}
+
+impl std::fmt::Display for NamedInstruction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mnemonic = format!("{:<4}", self.instruction.mnemonic());
+        if self.color {
+            write!(f, "{}", mnemonic.cyan())?;
+        } else {
+            write!(f, "{mnemonic}")?;
+        }
+        for operand in self.instruction.operands() {
+            let name = match operand {
+                Operand::Location(Location::Address(address)) => self.labels.get(address.0),
+                _ => operand
+                    .register()
+                    .and_then(|register| self.names[register.0].as_deref()),
+            };
+            let text = match name {
+                Some(name) => name.to_string(),
+                None => operand.to_string(),
+            };
+            if !self.color {
+                write!(f, " {text}")?;
+                continue;
+            }
+            match operand {
+                Operand::Location(Location::Address(_)) => write!(f, " {}", text.blue())?,
+                Operand::Value(Value::Literal(_)) => write!(f, " {}", text.green())?,
+                _ => write!(f, " {}", text.yellow())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Operand {
+    Register(Register),
+    Value(Value),
+    Location(Location),
+}
+
+impl Operand {
+    pub fn encode(&self) -> u16 {
+        match *self {
+            Operand::Value(Value::Literal(literal)) => literal.0,
+            Operand::Location(Location::Address(address)) => address.0 as u16,
+            Operand::Register(register)
+            | Operand::Value(Value::LiteralAtRegister(register))
+            | Operand::Location(Location::Register(register)) => 32768 + register.0 as u16,
+        }
+    }
+
+    pub fn register(&self) -> Option<Register> {
+        match *self {
+            Operand::Register(register)
+            | Operand::Value(Value::LiteralAtRegister(register))
+            | Operand::Location(Location::Register(register)) => Some(register),
+            Operand::Value(Value::Literal(_)) | Operand::Location(Location::Address(_)) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Register(register) => write!(f, "{register}"),
+            Operand::Value(value) => write!(f, "{value}"),
+            Operand::Location(location) => write!(f, "{location}"),
+        }
+    }
+}
+
+struct Decoder<'a> {
+    mem: &'a [u16],
+    start: usize,
+    index: usize,
+}
+
+impl Decoder<'_> {
+    fn read_mem(&mut self) -> Result<u16, VmError> {
+        let mem = self
+            .mem
+            .get(self.index)
+            .copied()
+            .ok_or(VmError::OutOfBounds {
+                pc: self.start,
+                addr: self.index,
+            })?;
+        self.index += 1;
+        Ok(mem)
+    }
+
+    fn read_operand<T>(&mut self, parse: fn(u16) -> Result<T, VmError>) -> Result<T, VmError> {
+        let operand = self.index - self.start;
+        let value = self.read_mem()?;
+        parse(value).map_err(|_| VmError::BadOperand {
+            addr: self.start,
+            mnemonic: OPCODES[self.mem[self.start] as usize].0,
+            operand,
+            value,
+        })
+    }
+
+    fn read_register(&mut self) -> Result<Register, VmError> {
+        self.read_operand(Register::new)
+    }
+
+    fn read_value(&mut self) -> Result<Value, VmError> {
+        self.read_operand(Value::new)
+    }
+
+    fn read_location(&mut self) -> Result<Location, VmError> {
+        self.read_operand(Location::new)
+    }
+
+    fn read_instruction(&mut self) -> Result<Instruction, VmError> {
+        let addr = self.index;
+        self.start = addr;
+        let opcode = self.read_mem()?;
+        Ok(match opcode {
+            0 => Instruction::Halt,
+            1 => Instruction::Set(self.read_register()?, self.read_value()?),
+            2 => Instruction::Push(self.read_value()?),
+            3 => Instruction::Pop(self.read_location()?),
+            4 => Instruction::Eq(
+                self.read_location()?,
+                self.read_value()?,
+                self.read_value()?,
+            ),
+            5 => Instruction::Gt(
+                self.read_location()?,
+                self.read_value()?,
+                self.read_value()?,
+            ),
+            6 => Instruction::Jmp(self.read_location()?),
+            7 => Instruction::Jt(self.read_value()?, self.read_location()?),
+            8 => Instruction::Jf(self.read_value()?, self.read_location()?),
+            9 => Instruction::Add(
+                self.read_location()?,
+                self.read_value()?,
+                self.read_value()?,
+            ),
+            10 => Instruction::Mult(
+                self.read_location()?,
+                self.read_value()?,
+                self.read_value()?,
+            ),
+            11 => Instruction::Mod(
+                self.read_location()?,
+                self.read_value()?,
+                self.read_value()?,
+            ),
+            12 => Instruction::And(
+                self.read_location()?,
+                self.read_value()?,
+                self.read_value()?,
+            ),
+            13 => Instruction::Or(
+                self.read_location()?,
+                self.read_value()?,
+                self.read_value()?,
+            ),
+            14 => Instruction::Not(self.read_location()?, self.read_value()?),
+            15 => Instruction::Rmem(self.read_location()?, self.read_location()?),
+            16 => Instruction::Wmem(self.read_location()?, self.read_value()?),
+            17 => Instruction::Call(self.read_location()?),
+            18 => Instruction::Ret,
+            19 => Instruction::Out(self.read_value()?),
+            20 => Instruction::In(self.read_location()?),
+            21 => Instruction::Noop,
+            _ => return Err(VmError::BadOpcode { addr, opcode }),
+        })
+    }
+}
+
+pub fn parse_number(text: &str) -> color_eyre::Result<u16> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => text.parse::<u16>(),
+    }
+    .wrap_err_with(|| format!("parse number: {text}"))
+}
+
+fn unescape(text: &str) -> color_eyre::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            bytes.push(ch as u8);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex = chars.by_ref().take(2).collect::<String>();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .wrap_err_with(|| format!("got weird escape: \\x{hex}"))?;
+                bytes.push(byte);
+            }
+            Some(other) => return Err(color_eyre::eyre::eyre!("got weird escape: \\{other}")),
+            None => return Err(color_eyre::eyre::eyre!("got trailing backslash")),
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn report_command_error(err: &color_eyre::Report) {
+    let causes = err.chain().map(ToString::to_string).collect::<Vec<_>>();
+    eprintln!("error: {}", causes.join(": "));
+}
+
+fn checked_pc(addr: usize, pc: usize) -> Result<u16, VmError> {
+    u16::try_from(pc)
+        .ok()
+        .filter(|&pc| pc <= 0x7fff)
+        .ok_or(VmError::PcOutOfRange { addr, pc })
+}
+
+fn parse_address(text: &str) -> color_eyre::Result<usize> {
+    let address = parse_number(text)? as usize;
+    if address < MEM_SIZE {
+        Ok(address)
+    } else {
+        Err(color_eyre::eyre::eyre!("got weird address: {text}"))
+    }
+}
+
+pub fn decode_at(mem: &[u16], addr: usize) -> Result<Instruction, VmError> {
+    Decoder {
+        mem,
+        start: addr,
+        index: addr,
+    }
+    .read_instruction()
+}
+
+pub struct Instructions<'a> {
+    mem: &'a [u16],
+    addr: usize,
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = (usize, Result<Instruction, VmError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.addr >= self.mem.len() {
+            return None;
+        }
+
+        let addr = self.addr;
+        let decoded = decode_at(self.mem, addr);
+        self.addr += decoded.as_ref().map_or(1, Instruction::width);
+        Some((addr, decoded))
+    }
+}
+
+pub fn instructions(mem: &[u16]) -> Instructions<'_> {
+    Instructions { mem, addr: 0 }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Register(usize);
+
+impl Register {
+    pub fn new(register: u16) -> Result<Self, VmError> {
+        if (32768..=32775).contains(&register) {
+            Ok(Self(register as usize - 32768))
+        } else {
+            Err(VmError::BadRegister { value: register })
+        }
+    }
+}
+
+impl std::fmt::Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "r{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Value {
+    Literal(Literal),
+    LiteralAtRegister(Register),
+}
+
+impl Value {
+    pub fn new(value: u16) -> Result<Self, VmError> {
+        match value {
+            0..=32767 => Ok(Value::Literal(Literal(value))),
+            32768..=32775 => Ok(Value::LiteralAtRegister(Register(value as usize - 32768))),
+            _ => Err(VmError::BadValue { value }),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Literal(literal) => write!(f, "{literal}"),
+            Value::LiteralAtRegister(register) => write!(f, "{register}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Literal(u16);
+
+impl Literal {
+    pub fn new(literal: u16) -> Result<Self, VmError> {
+        if (0..=32767).contains(&literal) {
+            Ok(Self(literal))
+        } else {
+            Err(VmError::BadValue { value: literal })
+        }
+    }
+}
+
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Location {
+    Address(Address),
+    Register(Register),
+}
+
+impl Location {
+    pub fn new(location: u16) -> Result<Self, VmError> {
+        match location {
+            0..=32767 => Ok(Location::Address(Address(location as usize))),
+            32768..=32775 => Ok(Location::Register(Register(location as usize - 32768))),
+            _ => Err(VmError::BadValue { value: location }),
+        }
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Address(address) => write!(f, "{address}"),
+            Location::Register(register) => write!(f, "{register}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Address(usize);
+
+impl Address {
+    pub fn new(address: u16) -> Result<Self, VmError> {
+        if (0..=32767).contains(&address) {
+            Ok(Self(address as usize))
+        } else {
+            Err(VmError::BadValue { value: address })
+        }
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#04x}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmError {
+    BadOpcode {
+        addr: usize,
+        opcode: u16,
+    },
+    BadRegister {
+        value: u16,
+    },
+    BadOperand {
+        addr: usize,
+        mnemonic: &'static str,
+        operand: usize,
+        value: u16,
+    },
+    BadValue {
+        value: u16,
+    },
+    StackUnderflow {
+        addr: usize,
+        mnemonic: &'static str,
+    },
+    ValueOutOfRange {
+        addr: usize,
+        value: u16,
+    },
+    DivByZero {
+        addr: usize,
+        left: u16,
+        right: u16,
+    },
+    OutOfBounds {
+        pc: usize,
+        addr: usize,
+    },
+    PcOutOfRange {
+        addr: usize,
+        pc: usize,
+    },
+    InputEof,
+}
+
+impl std::fmt::Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmError::BadOpcode { addr, opcode } => {
+                write!(f, "got weird opcode at {addr:#06x}: {opcode}")
+            }
+            VmError::BadRegister { value } => write!(f, "got weird register: {value}"),
+            VmError::BadOperand {
+                addr,
+                mnemonic,
+                operand,
+                value,
+            } => write!(
+                f,
+                "got weird operand {operand} of {mnemonic} at {addr:#06x}: {value}"
+            ),
+            VmError::BadValue { value } => write!(f, "got weird value: {value}"),
+            VmError::StackUnderflow { addr, mnemonic } => {
+                write!(f, "stack underflow at {addr:#06x} ({mnemonic})")
+            }
+            VmError::ValueOutOfRange { addr, value } => {
+                write!(f, "got out-of-range value {value:#x} at {addr:#06x}")
+            }
+            VmError::DivByZero { addr, left, right } => {
+                write!(f, "division by zero at {addr:#06x}: {left} % {right}")
+            }
+            VmError::OutOfBounds { pc, addr } => {
+                write!(f, "address out of bounds at {pc:#06x}: {addr:#x}")
+            }
+            VmError::PcOutOfRange { addr, pc } => {
+                write!(f, "program counter out of range at {addr:#06x}: {pc:#x}")
+            }
+            VmError::InputEof => write!(f, "stdin has reached EOF"),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+#[derive(Default)]
+pub enum Output {
+    #[default]
+    Stdout,
+    Writer(Box<dyn Write>),
+    Capture(Vec<u8>),
+    Expect(transcript::Expected),
+}
+
+impl std::fmt::Debug for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Output::Stdout => write!(f, "Stdout"),
+            Output::Writer(_) => write!(f, "Writer"),
+            Output::Capture(captured) => f.debug_tuple("Capture").field(captured).finish(),
+            Output::Expect(expected) => f.debug_tuple("Expect").field(expected).finish(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub enum Input {
+    #[default]
+    Stdin,
+    Reader(Box<dyn BufRead>),
+}
+
+impl std::fmt::Debug for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Stdin => write!(f, "Stdin"),
+            Input::Reader(_) => write!(f, "Reader"),
+        }
+    }
+}
+
+pub struct Trace {
+    pub writer: Box<dyn Write>,
+    pub range: Option<Range<usize>>,
+    pub color: bool,
+}
+
+impl std::fmt::Debug for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Trace")
+            .field("range", &self.range)
+            .field("color", &self.color)
+            .finish()
+    }
+}
+
+pub struct JsonTrace(pub Box<dyn Write>);
+
+impl std::fmt::Debug for JsonTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("JsonTrace").finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Continue,
+    Halt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Continue,
+    Halt,
+    NeedInput,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Step {
+    pub addr: usize,
+    pub instruction: Instruction,
+    pub outcome: Outcome,
+}
+
+pub const MEM_SIZE: usize = 1 << 15;
+
+const SLOTS: u8 = 9;
+
+const TELEPORTER_CHECK: usize = 0x154b;
+
+const CONFIRMATION_ROUTINE: usize = 0x178b;
+
+const DIFF_LIMIT: usize = 50;
+
+pub const HEADLESS_INSTRUCTION_BUDGET: u64 = 100_000_000;
+
+const GAME_ALIASES: &[(&str, &str)] = &[
+    ("n", "go north"),
+    ("s", "go south"),
+    ("e", "go east"),
+    ("w", "go west"),
+    ("l", "look"),
+    ("i", "inv"),
+    ("t", "take"),
+    ("d", "drop"),
+    ("u", "use"),
+];
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Machine {
+    mem: Vec<u16>,
+    registers: Box<[u16; 8]>,
+    stack: Vec<u16>,
+    index: usize,
+    stdin: VecDeque<u8>,
+    #[serde(default)]
+    register_names: RegisterNames,
+    #[serde(default)]
+    call_depth: usize,
+    #[serde(default)]
+    cycles: u64,
+    #[serde(default)]
+    log_path: Option<PathBuf>,
+    #[serde(default)]
+    pub labels: LabelMap,
+    #[serde(default)]
+    pub codes: Vec<(String, u64)>,
+    #[serde(default)]
+    awaiting_input: bool,
+    #[serde(default)]
+    program_len: usize,
+    #[serde(skip)]
+    initial: Vec<u16>,
+    #[serde(skip)]
+    pub max_instructions: Option<u64>,
+    #[serde(skip)]
+    pub strict: bool,
+    #[serde(skip)]
+    pub teleporter_bypass: Option<u16>,
+    #[serde(skip)]
+    pub state_dir: PathBuf,
+    /// Stop cleanly when input runs out during `in`; off by default, so the binary reports EOF as an error.
+    #[serde(skip)]
+    pub halt_on_eof: bool,
+    #[serde(skip)]
+    pub eof_redo_command: Option<String>,
+    #[serde(skip)]
+    pub input_echo: bool,
+    #[serde(skip)]
+    pub raw_input: bool,
+    #[serde(skip)]
+    pub color: bool,
+    #[serde(skip)]
+    pub trace_out: bool,
+    #[serde(skip)]
+    pub watch_stdin_ahead: bool,
+    #[serde(skip)]
+    at_input_line_start: bool,
+    #[serde(skip)]
+    pub game_aliases: Option<HashMap<String, String>>,
+    #[serde(skip)]
+    logger: Option<File>,
+    #[serde(skip)]
+    recorder: Option<File>,
+    #[serde(skip)]
+    pub trace: Option<Trace>,
+    #[serde(skip)]
+    pub trace_coalesce: bool,
+    #[serde(skip)]
+    pub trace_json: Option<JsonTrace>,
+    #[serde(skip)]
+    coalesced: Option<(usize, String)>,
+    #[serde(skip)]
+    pub input: Input,
+    #[serde(skip)]
+    pub output: Output,
+    /// Flushed at each newline and before blocking on input, so prompts without a newline still show.
+    #[serde(skip)]
+    pending_output: Vec<u8>,
+    #[serde(skip)]
+    output_line: String,
+    #[serde(skip)]
+    pub stats: Option<RunStats>,
+    #[serde(skip)]
+    pub call_graph: Option<CallGraph>,
+    #[serde(skip)]
+    pub map: Option<GameMap>,
+    #[serde(skip)]
+    decoded: Option<Vec<Option<Instruction>>>,
+    #[serde(skip)]
+    pub debugger: Debugger,
+}
+
+impl Machine {
+    pub fn new(program: &[u8]) -> Self {
+        let mut mem = vec![0; MEM_SIZE];
+        for (i, val) in program
+            .chunks_exact(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .take(MEM_SIZE)
+            .enumerate()
+        {
+            mem[i] = val;
+        }
+        let program_len = (program.len() / 2).min(MEM_SIZE);
+        let initial = mem[..program_len].to_vec();
+
+        Self {
+            mem,
+            registers: Box::new([0; 8]),
+            stack: Vec::new(),
+            index: 0,
+            stdin: VecDeque::new(),
+            register_names: Default::default(),
+            call_depth: 0,
+            cycles: 0,
+            log_path: None,
+            labels: LabelMap::default(),
+            codes: Vec::new(),
+            awaiting_input: false,
+            program_len,
+            initial,
+            max_instructions: None,
+            strict: false,
+            teleporter_bypass: None,
+            state_dir: PathBuf::from("states"),
+            halt_on_eof: false,
+            eof_redo_command: None,
+            input_echo: false,
+            raw_input: false,
+            color: false,
+            trace_out: false,
+            watch_stdin_ahead: false,
+            at_input_line_start: true,
+            game_aliases: Some(
+                GAME_ALIASES
+                    .iter()
+                    .map(|&(short, expansion)| (short.to_string(), expansion.to_string()))
+                    .collect(),
+            ),
+            logger: None,
+            recorder: None,
+            trace: None,
+            trace_coalesce: false,
+            trace_json: None,
+            coalesced: None,
+            input: Input::Stdin,
+            output: Output::Stdout,
+            pending_output: Vec::new(),
+            output_line: String::new(),
+            stats: None,
+            call_graph: None,
+            map: None,
+            decoded: None,
+            debugger: Debugger::default(),
+        }
+    }
+
+    pub fn run_headless(program: &[u8], input: &str) -> color_eyre::Result<String> {
+        let mut machine = Self::new(program);
+        machine.input = Input::Reader(Box::new(Cursor::new(input.as_bytes().to_vec())));
+        machine.output = Output::Capture(Vec::new());
+        machine.max_instructions = Some(HEADLESS_INSTRUCTION_BUDGET);
+        machine.game_aliases = None;
+        machine.halt_on_eof = true;
+
+        machine.run()?;
+
+        let Output::Capture(output) = machine.output else {
+            unreachable!("output is always captured when running headless");
+        };
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    pub fn from_state_bytes(bytes: &[u8]) -> color_eyre::Result<Self> {
+        let saved = Self::decode_state(bytes)?;
+        let program_len = (saved.program_len > 0).then_some(saved.program_len);
+        let mut machine = Self::new(&saved.image_bytes(program_len));
+        machine.restore(saved)?;
+
+        Ok(machine)
+    }
+
+    fn decode_state(bytes: &[u8]) -> color_eyre::Result<Self> {
+        let machine = match postcard::from_bytes::<Self>(bytes) {
+            Ok(machine) if machine.mem.len() == MEM_SIZE => machine,
+            _ => serde_json::from_slice(bytes).wrap_err("deserialize state")?,
+        };
+        if machine.mem.len() != MEM_SIZE {
+            return Err(color_eyre::eyre::eyre!(
+                "got weird memory size in state: {}",
+                machine.mem.len()
+            ));
+        }
+        if machine.index >= MEM_SIZE {
+            return Err(color_eyre::eyre::eyre!(
+                "got weird index in state: {:#x}",
+                machine.index
+            ));
+        }
+
+        Ok(machine)
+    }
+
+    pub fn load_state(&mut self, bytes: &[u8]) -> color_eyre::Result<()> {
+        let saved = Self::decode_state(bytes)?;
+        self.restore(saved)
+    }
+
+    fn restore(&mut self, saved: Self) -> color_eyre::Result<()> {
+        let Self {
+            mem,
+            registers,
+            stack,
+            index,
+            stdin,
+            register_names,
+            call_depth,
+            cycles,
+            log_path,
+            labels,
+            codes,
+            awaiting_input,
+            ..
+        } = saved;
+        self.mem = mem;
+        self.clear_decoded();
+        self.registers = registers;
+        self.stack = stack;
+        self.index = index;
+        let queued = std::mem::replace(&mut self.stdin, stdin);
+        self.stdin.extend(queued);
+        self.register_names = register_names;
+        self.call_depth = call_depth;
+        self.cycles = cycles;
+        self.logger = match log_path {
+            Some(ref path) => Some(
+                File::options()
+                    .append(true)
+                    .create(true)
+                    .open(path)
+                    .wrap_err("reopen logfile")?,
+            ),
+            None => None,
+        };
+        self.log_path = log_path;
+        self.labels = labels;
+        self.codes = codes;
+        self.awaiting_input = awaiting_input
+            && self
+                .index
+                .checked_sub(2)
+                .is_some_and(|pc| matches!(decode_at(&self.mem, pc), Ok(Instruction::In(_))));
+
+        Ok(())
+    }
+
+    pub fn set_logfile(&mut self, path: impl AsRef<Path>) -> color_eyre::Result<()> {
+        let file = File::create(&path).wrap_err("create logfile")?;
+        self.logger = Some(file);
+        self.log_path = Some(path.as_ref().to_path_buf());
+
+        Ok(())
+    }
+
+    pub fn set_recording(&mut self, path: impl AsRef<Path>) -> color_eyre::Result<()> {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err("open recording")?;
+        self.recorder = Some(file);
+
+        Ok(())
+    }
+
+    pub fn feed_input(&mut self, bytes: &[u8]) {
+        self.stdin
+            .extend(bytes.iter().copied().filter(|&byte| byte != b'\r'));
+    }
+
+    pub fn program(&self) -> &[u16] {
+        &self.mem[..self.program_len]
+    }
+
+    pub fn registers(&self) -> [u16; 8] {
+        *self.registers
+    }
+
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn image_bytes(&self, len: Option<usize>) -> Vec<u8> {
+        let len = len.unwrap_or_else(|| {
+            self.mem
+                .iter()
+                .rposition(|&word| word != 0)
+                .map_or(0, |last| last + 1)
+        });
+        self.mem[..len.min(MEM_SIZE)]
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    pub fn mem_bytes(&self) -> Vec<u8> {
+        self.mem
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    fn read_instruction(&mut self) -> color_eyre::Result<Instruction> {
+        if let Some(r7) = self.teleporter_bypass {
+            match self.index {
+                TELEPORTER_CHECK => self.registers[7] = r7,
+                CONFIRMATION_ROUTINE => {
+                    eprintln!("hacking...");
+                    self.mem[CONFIRMATION_ROUTINE] = 18;
+                    self.invalidate_decoded(CONFIRMATION_ROUTINE);
+                    self.registers[0] = 0x6;
+                }
+                _ => {}
+            }
+        }
+        let cached = self
+            .decoded
+            .as_mut()
+            .and_then(|decoded| decoded.get_mut(self.index));
+        let instruction = match cached {
+            Some(Some(instruction)) => *instruction,
+            Some(slot) => *slot.insert(decode_at(&self.mem, self.index)?),
+            None => decode_at(&self.mem, self.index)?,
+        };
+        self.maybe_write_to_logger(&instruction)?;
+        self.index += instruction.width();
+
+        Ok(instruction)
+    }
+
+    pub fn load_program(&mut self, program: &[u8]) -> color_eyre::Result<()> {
+        if !program.len().is_multiple_of(2) {
+            return Err(color_eyre::eyre::eyre!(
+                "program has an odd number of bytes: {}",
+                program.len()
+            ));
+        }
+
+        let loaded = Self::new(program);
+        self.initial = loaded.initial;
+        self.program_len = loaded.program_len;
+        self.reset();
+
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        self.mem.fill(0);
+        self.mem[..self.initial.len()].copy_from_slice(&self.initial);
+        self.clear_decoded();
+        *self.registers = [0; 8];
+        self.stack.clear();
+        self.index = 0;
+        self.stdin.clear();
+        self.call_depth = 0;
+        self.cycles = 0;
+        self.at_input_line_start = true;
+        self.awaiting_input = false;
+        self.coalesced = None;
+    }
+
+    /// Roughly halves run time on the opening rooms of challenge.bin in release builds.
+    pub fn enable_decode_cache(&mut self) {
+        self.decoded = Some(vec![None; MEM_SIZE]);
+    }
+
+    fn invalidate_decoded(&mut self, addr: usize) {
+        if let Some(ref mut decoded) = self.decoded {
+            for cached in &mut decoded[addr.saturating_sub(3)..=addr] {
+                *cached = None;
+            }
+        }
+    }
+
+    fn clear_decoded(&mut self) {
+        if self.decoded.is_some() {
+            self.enable_decode_cache();
+        }
+    }
+
+    /// Re-executes an interrupted `in`: its pc has already advanced, but a handled command consumed no game input.
+    pub fn redo_stdin(&mut self) {
+        if !std::mem::take(&mut self.awaiting_input) {
+            return;
+        }
+        self.index -= 2;
+        if let Some(ref command) = self.eof_redo_command {
+            for ch in command.bytes().chain([b'\n']).rev() {
+                self.stdin.push_front(ch);
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> color_eyre::Result<String> {
+        let mut line = String::new();
+
+        let bytes_read = match self.input {
+            Input::Stdin => std::io::stdin().read_line(&mut line),
+            Input::Reader(ref mut reader) => reader.read_line(&mut line),
+        }
+        .wrap_err("read from stdin")?;
+        if bytes_read == 0 {
+            return Err(VmError::InputEof.into());
+        }
+
+        Ok(line)
+    }
+
+    fn read_stdin(&mut self) -> color_eyre::Result<Option<u16>> {
+        if self.stdin.is_empty() {
+            self.flush_output()?;
+            let line = self.read_line()?;
+            if let Some(ref mut recorder) = self.recorder {
+                recorder
+                    .write_all(line.as_bytes())
+                    .wrap_err("write to recording")?;
+            }
+            if self.dispatch_command(&line) {
+                return Ok(None);
+            }
+            let line = self.expand_game_alias(&line);
+            let bytes = if self.raw_input {
+                let mut bytes = match unescape(line.trim_end_matches(['\r', '\n'])) {
+                    Ok(bytes) => bytes,
+                    Err(err) => {
+                        report_command_error(&err);
+                        return self.read_stdin();
+                    }
+                };
+                bytes.push(b'\n');
+                bytes
+            } else {
+                line.chars()
+                    .filter_map(|ch| (ch != '\r').then_some(ch as u8))
+                    .collect()
+            };
+            if bytes.is_empty() {
+                return self.read_stdin();
+            }
+            self.begin_input_line(&bytes)?;
+            self.stdin.extend(bytes);
+        } else if self.at_input_line_start {
+            let len = self
+                .stdin
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(self.stdin.len(), |newline| newline + 1);
+            let bytes = self.stdin.drain(..len).collect::<Vec<_>>();
+            let line = bytes.iter().map(|&byte| byte as char).collect::<String>();
+            if self.watch_stdin_ahead {
+                eprintln!("[next input] {}", line.trim_end_matches('\n'));
+            }
+            if self.dispatch_command(&line) {
+                return Ok(None);
+            }
+            self.begin_input_line(&bytes)?;
+            for &byte in bytes.iter().rev() {
+                self.stdin.push_front(byte);
+            }
+        }
+
+        let raw = self.stdin.pop_front().wrap_err("stdin is empty")?;
+        self.at_input_line_start = raw == b'\n';
+        Ok(Some(raw as u16))
+    }
+
+    fn begin_input_line(&mut self, bytes: &[u8]) -> color_eyre::Result<()> {
+        if self.input_echo {
+            for &byte in bytes {
+                self.write_stdout(u16::from(byte))?;
+            }
+        }
+        if let Some(ref mut map) = self.map {
+            let line = bytes.iter().map(|&byte| byte as char).collect::<String>();
+            map.record_input(&line);
+        }
+
+        Ok(())
+    }
+
+    fn expand_game_alias(&self, line: &str) -> String {
+        let Some(ref aliases) = self.game_aliases else {
+            return line.to_string();
+        };
+
+        let trimmed = line.trim_start();
+        let (verb, rest) = trimmed
+            .find(char::is_whitespace)
+            .map_or((trimmed, ""), |end| trimmed.split_at(end));
+        match aliases.get(verb) {
+            Some(expansion) => format!("{expansion}{rest}"),
+            None => line.to_string(),
+        }
+    }
+
+    fn dispatch_command(&mut self, line: &str) -> bool {
+        match self.run_command(line) {
+            Ok(handled) => handled,
+            Err(err) => {
+                report_command_error(&err);
+                true
+            }
+        }
+    }
+
+    fn run_command(&mut self, line: &str) -> color_eyre::Result<bool> {
+        if line.starts_with("savebin") {
+            let mut iter = line.split_whitespace().skip(1);
+            let filename = iter.next().wrap_err("get filename")?;
+            let len = iter.next().map(parse_number).transpose()?;
+            let image = self.image_bytes(len.map(usize::from));
+            std::fs::write(filename, &image).wrap_err("save binary")?;
+            println!("saved {} words", image.len() / 2);
+        } else if line.starts_with("savestate-bin") {
+            let (_, filename) = line.split_once(' ').wrap_err("get filename")?;
+            let state = postcard::to_stdvec(self).wrap_err("serialize state")?;
+            std::fs::write(filename.trim(), &state).wrap_err("save state")?;
+            println!("saved {} bytes", state.len());
+        } else if line.starts_with("savestate") {
+            let (_, filename) = line.split_once(' ').wrap_err("get filename")?;
+            let filename = filename.trim();
+            std::fs::write(
+                filename,
+                serde_json::to_string(self).wrap_err("serialize state")?,
+            )
+            .wrap_err("save state")?;
+
+            std::process::exit(0);
+        } else if line.starts_with("loadstate") {
+            let (_, filename) = line.split_once(' ').wrap_err("get filename")?;
+            let filename = filename.trim();
+            let state = std::fs::read(filename).wrap_err("load state")?;
+            self.load_state(&state)?;
+        } else if let Some(slot) = line.strip_prefix("save ") {
+            let path = self.slot_path(slot)?;
+            std::fs::create_dir_all(&self.state_dir).wrap_err("create state directory")?;
+            std::fs::write(
+                &path,
+                serde_json::to_string(self).wrap_err("serialize state")?,
+            )
+            .wrap_err("save state")?;
+            println!("saved {}", path.display());
+        } else if let Some(slot) = line.strip_prefix("load ") {
+            let path = self.slot_path(slot)?;
+            let state = std::fs::read(path).wrap_err("load state")?;
+            self.load_state(&state)?;
+        } else if line.starts_with("slots") {
+            self.print_slots();
+        } else if line.starts_with("profile") {
+            let mut iter = line.split_whitespace().skip(1);
+            match iter.next() {
+                Some("on") => {
+                    self.stats.get_or_insert_with(RunStats::default);
+                }
+                Some("off") => self.stats = None,
+                Some("dump") => {
+                    let filename = iter.next().wrap_err("get filename")?;
+                    let stats = self.stats.as_ref().wrap_err("profiling is off")?;
+                    let mut file = File::create(filename).wrap_err("create profile dump")?;
+                    for (addr, hits) in stats.hot_addresses() {
+                        writeln!(file, "{addr:#06x} {hits}").wrap_err("write profile dump")?;
+                    }
+                }
+                other => {
+                    return Err(color_eyre::eyre::eyre!(
+                        "got weird profile argument: {other:?}"
+                    ))
+                }
+            }
+        } else if line.starts_with("codes") {
+            if self.codes.is_empty() {
+                println!("no codes found yet");
+            }
+            for (i, (code, cycle)) in self.codes.iter().enumerate() {
+                println!("#{} {code} (cycle {cycle})", i + 1);
+            }
+        } else if line.starts_with("map") {
+            let mut iter = line.split_whitespace().skip(1);
+            match iter.next() {
+                Some("on") => {
+                    self.map.get_or_insert_with(GameMap::default);
+                }
+                Some("off") => self.map = None,
+                Some("export") => {
+                    let filename = iter.next().wrap_err("get filename")?;
+                    let map = self.map.as_ref().wrap_err("mapping is off")?;
+                    let text = if filename.ends_with(".dot") {
+                        map.to_dot()
+                    } else {
+                        serde_json::to_string_pretty(map).wrap_err("serialize map")?
+                    };
+                    std::fs::write(filename, text).wrap_err("export map")?;
+                }
+                other => return Err(color_eyre::eyre::eyre!("got weird map argument: {other:?}")),
+            }
+        } else if line.starts_with("calltrace") {
+            match line.split_whitespace().nth(1) {
+                Some("on") => {
+                    self.call_graph.get_or_insert_with(CallGraph::default);
+                }
+                Some("off") => self.call_graph = None,
+                other => {
+                    return Err(color_eyre::eyre::eyre!(
+                        "got weird calltrace argument: {other:?}"
+                    ))
+                }
+            }
+        } else if line.starts_with("functions") {
+            let call_graph = self.call_graph.as_ref().wrap_err("call tracing is off")?;
+            for (function, calls) in &call_graph.functions {
+                println!(
+                    "{function:#06x}    called {calls} times from {} sites",
+                    call_graph.callers[function].len()
+                );
+            }
+            if call_graph.unmatched_returns > 0 {
+                println!(
+                    "{} returns did not match a traced call",
+                    call_graph.unmatched_returns
+                );
+            }
+        } else if line.starts_with("disasm-follow") {
+            for (addr, text) in self.disassemble_reachable() {
+                println!("{addr:#06x}    {text}");
+            }
+        } else if line.starts_with("disasm") {
+            let mut iter = line.split_whitespace().skip(1);
+            let start = parse_address(iter.next().wrap_err("get disasm start")?)?;
+            let len = parse_number(iter.next().wrap_err("get disasm length")?)? as usize;
+            for (addr, text) in self.disassemble_range(start, len) {
+                println!("{addr:#06x}    {text}");
+            }
+        } else if line.starts_with("labels") {
+            let (_, filename) = line.split_once(' ').wrap_err("get filename")?;
+            let text = std::fs::read_to_string(filename.trim()).wrap_err("read labels")?;
+            self.labels.load(&text)?;
+        } else if line.starts_with("label") {
+            let mut iter = line.split_whitespace().skip(1);
+            let addr = parse_address(iter.next().wrap_err("get label address")?)?;
+            let name = iter.next().wrap_err("get label name")?;
+            self.labels.insert(addr, name)?;
+        } else if line.starts_with("finds") {
+            let text = line.strip_prefix("finds").unwrap_or_default().trim();
+            let text = text
+                .strip_prefix('"')
+                .and_then(|text| text.strip_suffix('"'))
+                .unwrap_or(text);
+            let pattern = unescape(text)?
+                .into_iter()
+                .map(u16::from)
+                .collect::<Vec<_>>();
+            self.print_matches(&pattern);
+        } else if line.starts_with("find") {
+            let pattern = line
+                .split_whitespace()
+                .skip(1)
+                .map(parse_number)
+                .collect::<color_eyre::Result<Vec<_>>>()?;
+            self.print_matches(&pattern);
+        } else if line.starts_with("memdump") {
+            let mut iter = line.split_whitespace().skip(1);
+            let start = parse_address(iter.next().wrap_err("get memdump start")?)?;
+            let len = parse_number(iter.next().wrap_err("get memdump length")?)? as usize;
+            self.print_memdump(start, len)?;
+        } else if line.starts_with("dumpregs") {
+            for (register, value) in self.registers().into_iter().enumerate() {
+                self.print_register_value(register, value);
+            }
+        } else if line.starts_with("dumpstack") {
+            let depth = match line.split_whitespace().nth(1) {
+                Some(depth) => depth
+                    .parse::<usize>()
+                    .wrap_err("parse stack depth into usize")?,
+                None => self.stack.len(),
+            };
+            self.print_stack(depth);
+        } else if line.starts_with("dumpreg") {
+            let (_, reg) = line.split_once(' ').wrap_err("get register")?;
+            let reg = self.parse_register(reg.trim())?;
+            self.print_register(reg);
+        } else if line.starts_with("name") {
+            let mut iter = line.split_whitespace().skip(1);
+            let reg = iter
+                .next()
+                .ok_or_else(|| color_eyre::eyre::eyre!("get register"))?;
+            let reg = self.parse_register(reg)?;
+            let name = iter
+                .next()
+                .ok_or_else(|| color_eyre::eyre::eyre!("get name"))?;
+            if name.parse::<usize>().is_ok()
+                || name.starts_with('r') && name[1..].parse::<usize>().is_ok()
+            {
+                return Err(color_eyre::eyre::eyre!(
+                    "name would shadow a register: {name}"
+                ));
+            }
+            self.register_names[reg] = Some(name.to_string());
+        } else if line.starts_with("diff") {
+            let limit = match line.split_whitespace().nth(1) {
+                Some(limit) => limit
+                    .parse::<usize>()
+                    .wrap_err("parse diff limit into usize")?,
+                None => DIFF_LIMIT,
+            };
+            self.print_diff(limit);
+        } else if line.starts_with("patch") {
+            let mut iter = line.split_whitespace().skip(1);
+            let addr = parse_address(
+                iter.next()
+                    .ok_or_else(|| color_eyre::eyre::eyre!("get patch address"))?,
+            )?;
+            let value = parse_number(
+                iter.next()
+                    .ok_or_else(|| color_eyre::eyre::eyre!("get patch value"))?,
+            )?;
+            let old = std::mem::replace(&mut self.mem[addr], value);
+            self.invalidate_decoded(addr);
+            println!("{addr:#06x}: {old:#06x} -> {value:#06x}");
+        } else if line.starts_with("setreg") {
+            let mut iter = line.trim().splitn(3, ' ');
+            let _ = iter
+                .next()
+                .ok_or_else(|| color_eyre::eyre::eyre!("something sketchy's happening"))?;
+            let reg = iter
+                .next()
+                .ok_or_else(|| color_eyre::eyre::eyre!("get register"))?;
+            let reg = self.parse_register(reg)?;
+            let val = parse_number(
+                iter.next()
+                    .ok_or_else(|| color_eyre::eyre::eyre!("get value"))?
+                    .trim(),
+            )?;
+            // Arithmetic keeps registers modulo 32768, and reading anything larger back
+            // as an operand faults, so only 15-bit literals are safe to set.
+            if val > 0x7fff {
+                return Err(color_eyre::eyre::eyre!(
+                    "register values are 15 bits, got {val:#x}"
+                ));
+            }
+            self.registers[reg] = val;
+        } else if line.starts_with("feed") {
+            let text = line.strip_prefix("feed").unwrap_or_default().trim_start();
+            self.stdin
+                .extend(unescape(text.trim_end_matches(['\r', '\n']))?);
+            self.stdin.push_back(b'\n');
+        } else if line.starts_with("break-depth") {
+            let (_, depth) = line.split_once(' ').wrap_err("get call depth")?;
+            self.debugger.break_depth = match depth.trim() {
+                "off" => None,
+                depth => Some(
+                    depth
+                        .parse::<usize>()
+                        .wrap_err("parse call depth into usize")?,
+                ),
+            };
+        } else if line.starts_with("breakpoints") {
+            self.print_breakpoints();
+        } else if line.starts_with("delete") {
+            match line.split_whitespace().nth(1) {
+                Some(index) => {
+                    let index = index
+                        .parse::<usize>()
+                        .wrap_err("parse breakpoint index into usize")?;
+                    if index >= self.debugger.breakpoints.len() {
+                        return Err(color_eyre::eyre::eyre!("no breakpoint #{index}"));
+                    }
+                    self.debugger.breakpoints.remove(index);
+                }
+                None => self.debugger.breakpoints.clear(),
+            }
+        } else if line.starts_with("breakstats") {
+            match line.split_whitespace().nth(1) {
+                Some("reset") => self.reset_breakpoint_hits(),
+                Some(other) => {
+                    return Err(color_eyre::eyre::eyre!(
+                        "got weird breakstats argument: {other}"
+                    ))
+                }
+                None => self.print_breakstats(),
+            }
+        } else if line.starts_with("history") {
+            let count = match line.split_whitespace().nth(1) {
+                Some(count) => count
+                    .parse::<usize>()
+                    .wrap_err("parse history length into usize")?,
+                None => self.debugger.history_len,
+            };
+            self.print_history(count);
+        } else if line.starts_with("solve-teleporter") {
+            match routine::find_magic_r7(
+                routine::RoutineParams::default(),
+                Some(&self.state_dir.join(routine::CACHE_FILE)),
+            )? {
+                Some(r7) => {
+                    self.registers[7] = r7;
+                    self.teleporter_bypass = Some(r7);
+                    println!("r7 = {r7:#x}, teleporter bypass enabled");
+                }
+                None => println!("no r7 value satisfies the teleporter routine"),
+            }
+        } else if line.starts_with("solve-coins") {
+            for coin in coins::solve_coins() {
+                println!("use {coin} coin");
+            }
+        } else if line.starts_with("solve-vault") {
+            for direction in grid::solve_vault() {
+                println!("{direction}");
+            }
+        } else if line.starts_with("teleport") {
+            let (_, value) = line.split_once(' ').wrap_err("get teleporter r7 value")?;
+            self.teleporter_bypass = match value.trim() {
+                "off" => None,
+                value => Some(parse_number(value)?),
+            };
+        } else if line.starts_with("undisplay") {
+            self.debugger.displays.clear();
+        } else if line.starts_with("display") {
+            let (_, args) = line.split_once(' ').wrap_err("get display targets")?;
+            self.add_displays(args)?;
+        } else if line.starts_with("watch") {
+            let (_, args) = line.split_once(' ').wrap_err("get watch target")?;
+            self.add_watch(args)?;
+        } else if line.starts_with("break") {
+            let (_, args) = line.split_once(' ').wrap_err("get breakpoint")?;
+            self.add_breakpoint(args)?;
+        } else if line.starts_with("targets") {
+            let targets = analysis::jump_targets(&self.mem);
+            for (target, references) in &targets.fixed {
+                println!("{target:#06x}    referenced by {references}");
+            }
+            println!("dynamic:");
+            for (addr, instruction) in &targets.dynamic {
+                println!(
+                    "{addr:#06x}    {}",
+                    instruction.named(&self.register_names, &self.labels)
+                );
+            }
+        } else if line.starts_with("trace-coalesce") {
+            self.trace_coalesce = match line.split_whitespace().nth(1) {
+                Some("on") => true,
+                Some("off") => false,
+                other => {
+                    return Err(color_eyre::eyre::eyre!(
+                        "got weird trace-coalesce argument: {other:?}"
+                    ))
+                }
+            };
+        } else if line.starts_with("strict") {
+            self.strict = match line.split_whitespace().nth(1) {
+                Some("on") => true,
+                Some("off") => false,
+                other => return Err(color_eyre::eyre::eyre!("got weird strict mode: {other:?}")),
+            };
+        } else if line.starts_with("peekout") {
+            println!("{:?}", String::from_utf8_lossy(&self.pending_output));
+        } else if line.starts_with("gamealias") {
+            let mut iter = line.split_whitespace().skip(1);
+            let short = iter
+                .next()
+                .ok_or_else(|| color_eyre::eyre::eyre!("get alias"))?;
+            let expansion = iter.collect::<Vec<_>>().join(" ");
+            let aliases = self
+                .game_aliases
+                .as_mut()
+                .ok_or_else(|| color_eyre::eyre::eyre!("game aliases are disabled"))?;
+            if expansion.is_empty() {
+                aliases.remove(short);
+            } else {
+                aliases.insert(short.to_string(), expansion);
+            }
+        } else if line.starts_with("mem-checksum") {
+            let mut iter = line.split_whitespace().skip(1);
+            let addr = parse_address(
+                iter.next()
+                    .ok_or_else(|| color_eyre::eyre::eyre!("get address"))?,
+            )?;
+            let len = parse_number(
+                iter.next()
+                    .ok_or_else(|| color_eyre::eyre::eyre!("get length"))?,
+            )? as usize;
+            let words = self.mem.get(addr..addr + len).ok_or_else(|| {
+                color_eyre::eyre::eyre!("range {addr:#x}+{len:#x} is out of bounds")
+            })?;
+            println!(
+                "checksum {addr:#06x}+{len:#x} = {:#010x}",
+                analysis::checksum(words)
+            );
+        } else if line.starts_with("logfile") {
+            let (_, filename) = line.split_once(' ').wrap_err("get filename")?;
+            let filename = filename.trim();
+            self.set_logfile(filename)?;
+        } else if line.starts_with("nolog") {
+            self.logger = None;
+            self.log_path = None;
+        } else {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    fn parse_register(&self, text: &str) -> color_eyre::Result<usize> {
+        if let Some(reg) = self
+            .register_names
+            .iter()
+            .position(|name| name.as_deref() == Some(text))
+        {
+            return Ok(reg);
+        }
+
+        let reg = text
+            .strip_prefix('r')
+            .unwrap_or(text)
+            .parse::<usize>()
+            .wrap_err("parse register into usize")?;
+        if reg < self.registers.len() {
+            Ok(reg)
+        } else {
+            Err(color_eyre::eyre::eyre!("got weird register: {text}"))
+        }
+    }
+
+    fn slot_path(&self, slot: &str) -> color_eyre::Result<PathBuf> {
+        let slot = slot.trim().parse::<u8>().wrap_err("parse slot into u8")?;
+        if !(1..=SLOTS).contains(&slot) {
+            return Err(color_eyre::eyre::eyre!("got weird slot: {slot}"));
+        }
+
+        Ok(self.state_dir.join(format!("slot{slot}.json")))
+    }
+
+    fn print_slots(&self) {
+        for slot in 1..=SLOTS {
+            let path = self.state_dir.join(format!("slot{slot}.json"));
+            let Ok(state) = std::fs::read(&path) else {
+                continue;
+            };
+            match serde_json::from_slice::<serde_json::Value>(&state) {
+                Ok(state) => println!(
+                    "slot {slot}: pc {:#06x}, r7 {:#x}",
+                    state["index"].as_u64().unwrap_or_default(),
+                    state["registers"][7].as_u64().unwrap_or_default()
+                ),
+                Err(err) => println!("slot {slot}: unreadable ({err})"),
+            }
+        }
+    }
+
+    fn print_matches(&self, pattern: &[u16]) {
+        let matches = analysis::find(&self.mem, pattern);
+        if matches.is_empty() {
+            println!("no matches");
+        }
+        for addr in matches {
+            println!("{}", Address(addr));
+        }
+    }
+
+    fn print_memdump(&self, start: usize, len: usize) -> color_eyre::Result<()> {
+        if start + len > self.mem.len() {
+            return Err(color_eyre::eyre::eyre!(
+                "memory range out of bounds: {start:#x}..{:#x}",
+                start + len
+            ));
+        }
+
+        for (row, words) in self.mem[start..start + len].chunks(8).enumerate() {
+            print!("{}:", Address(start + row * 8));
+            for word in words {
+                print!(" {word:04x}");
+            }
+            println!();
+        }
+
+        Ok(())
+    }
+
+    fn print_register(&self, reg: usize) {
+        self.print_register_value(reg, self.registers[reg]);
+    }
+
+    fn print_register_value(&self, reg: usize, value: u16) {
+        match &self.register_names[reg] {
+            Some(name) => println!("Register {reg} ({name}) = {value:#x}"),
+            None => println!("Register {reg} = {value:#x}"),
+        }
+    }
+
+    pub fn changed_words(&self) -> Vec<(usize, u16, u16)> {
+        self.mem
+            .iter()
+            .enumerate()
+            .filter_map(|(addr, &new)| {
+                let old = self.initial.get(addr).copied().unwrap_or(0);
+                (old != new).then_some((addr, old, new))
+            })
+            .collect()
+    }
+
+    fn print_diff(&self, limit: usize) {
+        let changed = self.changed_words();
+        if changed.is_empty() {
+            println!("memory matches the loaded program");
+        }
+        for &(addr, old, new) in changed.iter().take(limit) {
+            println!("{addr:#06x}: {old:#06x} -> {new:#06x}");
+        }
+        if changed.len() > limit {
+            println!("... {} more", changed.len() - limit);
+        }
+    }
+
+    fn print_stack(&self, depth: usize) {
+        if self.stack.is_empty() {
+            println!("stack is empty");
+        }
+        for (i, &value) in self.stack.iter().enumerate().rev().take(depth) {
+            let addr = value as usize;
+            let call_site = addr.checked_sub(2).and_then(|call| {
+                matches!(decode_at(&self.mem, call), Ok(Instruction::Call(_))).then_some(call)
+            });
+            match call_site {
+                Some(call) => println!("#{i:<3} {value:#06x}    return from call at {call:#06x}"),
+                None => println!("#{i:<3} {value:#06x}"),
+            }
+        }
+    }
+
+    fn eval_register(&self, register: Register) -> u16 {
+        self.registers[register.0]
+    }
+
+    fn eval_location(&self, pc: usize, location: Location) -> Result<Address, VmError> {
+        self.mem_index(pc, location).map(Address)
+    }
+
+    fn mem_index(&self, pc: usize, location: Location) -> Result<usize, VmError> {
+        let addr = match location {
+            Location::Address(address) => address.0,
+            Location::Register(register) => self.eval_register(register) as usize,
+        };
+        if addr >= self.mem.len() {
+            return Err(VmError::OutOfBounds { pc, addr });
+        }
+
+        Ok(addr)
+    }
+
+    fn eval_value(&self, pc: usize, value: Value) -> Result<Literal, VmError> {
+        match value {
+            Value::Literal(literal) => Ok(literal),
+            Value::LiteralAtRegister(register) => {
+                let value = self.eval_register(register);
+                Literal::new(value).map_err(|_| VmError::ValueOutOfRange { addr: pc, value })
+            }
+        }
+    }
+
+    fn write_to_location(&mut self, addr: usize, location: Location, raw: u16) {
+        let slot = match location {
+            Location::Address(address) => {
+                self.invalidate_decoded(address.0);
+                &mut self.mem[address.0]
+            }
+            Location::Register(register) => &mut self.registers[register.0],
+        };
+        let old = std::mem::replace(slot, raw);
+        self.check_watch(addr, location, old, raw);
+    }
+
+    fn maybe_write_to_logger(&mut self, instruction: &Instruction) -> color_eyre::Result<()> {
+        if self.logger.is_none() && self.trace.is_none() {
+            return Ok(());
+        }
+
+        if self.trace_coalesce {
+            if let Instruction::Out(value) = *instruction {
+                let ch = self
+                    .eval_value(self.index, value)
+                    .map_or('?', |literal| literal.0 as u8 as char);
+                self.coalesced
+                    .get_or_insert_with(|| (self.index, String::new()))
+                    .1
+                    .push(ch);
+                return Ok(());
+            }
+            if let Some((addr, text)) = self.coalesced.take() {
+                let colored = self
+                    .trace_color()
+                    .then(|| format!("{} {text:?}", "out ".cyan()));
+                self.write_log_line(addr, &format!("{:<4} {text:?}", "out"), colored.as_deref())?;
+            }
+        }
+
+        let named = instruction.named(&self.register_names, &self.labels);
+        let line = named.to_string();
+        let colored = self.trace_color().then(|| named.colored(true).to_string());
+        self.write_log_line(self.index, &line, colored.as_deref())
+    }
+
+    fn trace_color(&self) -> bool {
+        self.trace.as_ref().is_some_and(|trace| trace.color)
+    }
+
+    fn write_log_line(
+        &mut self,
+        addr: usize,
+        line: &str,
+        colored: Option<&str>,
+    ) -> color_eyre::Result<()> {
+        if let Some(ref mut logger) = self.logger {
+            writeln!(logger, "{addr:#06x}    {line}").wrap_err("write to logger")?;
+        }
+        if let Some(ref mut trace) = self.trace {
+            if trace
+                .range
+                .as_ref()
+                .is_none_or(|range| range.contains(&addr))
+            {
+                let line = colored.unwrap_or(line);
+                writeln!(trace.writer, "{addr:#06x}    {line}").wrap_err("write to trace")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn flush_output(&mut self) -> color_eyre::Result<()> {
+        if self.pending_output.is_empty() {
+            return Ok(());
+        }
+
+        match self.output {
+            Output::Writer(ref mut writer) => {
+                writer
+                    .write_all(&self.pending_output)
+                    .wrap_err("write to output")?;
+                writer.flush().wrap_err("flush output")?;
+            }
+            _ => {
+                let mut stdout = std::io::stdout().lock();
+                stdout
+                    .write_all(&self.pending_output)
+                    .wrap_err("write to stdout")?;
+                stdout.flush().wrap_err("flush stdout")?;
+            }
+        }
+        self.pending_output.clear();
+
+        Ok(())
+    }
+
+    fn write_stdout(&mut self, raw: u16) -> color_eyre::Result<()> {
+        if let Some(ref mut map) = self.map {
+            map.record_output(raw as u8);
+        }
+        self.record_codes(raw as u8);
+        match self.output {
+            Output::Stdout | Output::Writer(_) => {
+                self.pending_output.push(raw as u8);
+                if raw == u16::from(b'\n') {
+                    self.flush_output()?;
+                }
+            }
+            Output::Capture(ref mut captured) => captured.push(raw as u8),
+            Output::Expect(ref mut expected) => expected.check(raw as u8, self.cycles)?,
+        }
+
+        Ok(())
+    }
+
+    fn record_codes(&mut self, byte: u8) {
+        if byte != b'\n' {
+            self.output_line.push(byte as char);
+            return;
+        }
+
+        let line = std::mem::take(&mut self.output_line);
+        for code in analysis::codes_in(&line) {
+            if !self.codes.iter().any(|(known, _)| known == code) {
+                self.codes.push((code.to_string(), self.cycles));
+            }
+        }
+    }
+
+    fn push_stack(&mut self, addr: usize, value: u16) -> Result<(), VmError> {
+        if self.strict && value > 0x7fff {
+            return Err(VmError::ValueOutOfRange { addr, value });
+        }
+
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop_stack(&mut self, addr: usize, instruction: Instruction) -> Result<u16, VmError> {
+        self.stack.pop().ok_or(VmError::StackUnderflow {
+            addr,
+            mnemonic: instruction.mnemonic(),
+        })
+    }
+
+    fn execute(&mut self, addr: usize, instruction: Instruction) -> color_eyre::Result<Outcome> {
+        match instruction {
+            Instruction::Halt => return Ok(Outcome::Halt),
+            Instruction::Set(register, value) => {
+                let literal = self.eval_value(addr, value)?;
+                self.write_to_location(addr, Location::Register(register), literal.0)
+            }
+            Instruction::Push(value) => {
+                let raw = match value {
+                    Value::Literal(literal) => literal.0,
+                    Value::LiteralAtRegister(register) => self.eval_register(register),
+                };
+                self.push_stack(addr, raw)?
+            }
+            Instruction::Pop(location) => {
+                let raw = self.pop_stack(addr, instruction)?;
+                self.write_to_location(addr, location, raw)
+            }
+            Instruction::Eq(location, left, right) => {
+                let left = self.eval_value(addr, left)?;
+                let right = self.eval_value(addr, right)?;
+                self.write_to_location(addr, location, if left.0 == right.0 { 1 } else { 0 })
+            }
+            Instruction::Gt(location, left, right) => {
+                let left = self.eval_value(addr, left)?;
+                let right = self.eval_value(addr, right)?;
+                self.write_to_location(addr, location, if left.0 > right.0 { 1 } else { 0 })
+            }
+            Instruction::Jmp(location) => {
+                let address = self.eval_location(addr, location)?;
+                self.index = address.0
+            }
+            Instruction::Jt(value, location) => {
+                let literal = self.eval_value(addr, value)?;
+                let address = self.eval_location(addr, location)?;
+                if literal.0 != 0 {
+                    self.index = address.0
+                }
+            }
+            Instruction::Jf(value, location) => {
+                let literal = self.eval_value(addr, value)?;
+                let address = self.eval_location(addr, location)?;
+                if literal.0 == 0 {
+                    self.index = address.0
+                }
+            }
+            Instruction::Add(dest, left, right) => {
+                let left = self.eval_value(addr, left)?;
+                let right = self.eval_value(addr, right)?;
+                debug_assert!(left.0 < 32768 && right.0 < 32768);
+                let sum = ((left.0 as u32 + right.0 as u32) % 32768) as u16;
+                self.write_to_location(addr, dest, sum)
+            }
+            Instruction::Mult(dest, left, right) => {
+                let left = self.eval_value(addr, left)?;
+                let right = self.eval_value(addr, right)?;
+                let product = ((left.0 as u32 * right.0 as u32) % 32768) as u16;
+                self.write_to_location(addr, dest, product)
+            }
+            Instruction::Mod(dest, left, right) => {
+                let left = self.eval_value(addr, left)?;
+                let right = self.eval_value(addr, right)?;
+                if right.0 == 0 {
+                    return Err(VmError::DivByZero {
+                        addr,
+                        left: left.0,
+                        right: right.0,
+                    }
+                    .into());
+                }
+                let rem = left.0 % right.0;
+                self.write_to_location(addr, dest, rem)
+            }
+            Instruction::And(dest, left, right) => {
+                let left = self.eval_value(addr, left)?;
+                let right = self.eval_value(addr, right)?;
+                let anded = left.0 & right.0;
+                self.write_to_location(addr, dest, anded)
+            }
+            Instruction::Or(dest, left, right) => {
+                let left = self.eval_value(addr, left)?;
+                let right = self.eval_value(addr, right)?;
+                let ored = left.0 | right.0;
+                self.write_to_location(addr, dest, ored)
+            }
+            Instruction::Not(dest, operand) => {
+                let operand = self.eval_value(addr, operand)?;
+                let noted = !operand.0;
+                let noted = noted & 0x7fff;
+                self.write_to_location(addr, dest, noted)
+            }
+            Instruction::Rmem(dest, src) => {
+                let src = self.mem_index(addr, src)?;
+                let mem = self.mem[src];
+                self.write_to_location(addr, dest, mem)
+            }
+            Instruction::Wmem(dest, src) => {
+                let dest = self.mem_index(addr, dest)?;
+                let src = self.eval_value(addr, src)?;
+                self.write_to_location(addr, Location::Address(Address(dest)), src.0)
+            }
+            Instruction::Call(location) => {
+                let address = self.eval_location(addr, location)?;
+                let return_addr = checked_pc(addr, self.index)?;
+                self.stack.push(return_addr);
+                self.index = address.0;
+                self.call_depth += 1
+            }
+            Instruction::Ret => {
+                let dest = self.pop_stack(addr, instruction)? as usize;
+                checked_pc(addr, dest)?;
+                self.index = dest;
+                self.call_depth = self.call_depth.saturating_sub(1)
+            }
+            Instruction::Out(value) => {
+                let literal = self.eval_value(addr, value)?;
+                if self.trace_out {
+                    eprintln!(
+                        "[cycle {} at {addr:#06x}] {:?}",
+                        self.cycles, literal.0 as u8 as char
+                    );
+                }
+                self.write_stdout(literal.0)?
+            }
+            Instruction::In(location) => {
+                self.awaiting_input = true;
+                let raw = match self.read_stdin() {
+                    Err(err)
+                        if self.halt_on_eof
+                            && err.downcast_ref::<VmError>() == Some(&VmError::InputEof) =>
+                    {
+                        return Ok(Outcome::Halt)
+                    }
+                    raw => raw?,
+                };
+                match raw {
+                    Some(raw) => {
+                        self.awaiting_input = false;
+                        self.write_to_location(addr, location, raw)
+                    }
+                    None => self.redo_stdin(),
+                }
+            }
+            Instruction::Noop => {}
+        }
+
+        Ok(Outcome::Continue)
+    }
+
+    pub fn step_once(&mut self) -> color_eyre::Result<Step> {
+        let addr = self.index;
+        let instruction = self.read_instruction()?;
+        self.cycles += 1;
+        self.debugger.record_history(addr, instruction);
+        let outcome = self.execute(addr, instruction)?;
+        if let Some(ref mut stats) = self.stats {
+            stats.record(addr, &instruction, self.call_depth);
+        }
+        if let Some(ref mut call_graph) = self.call_graph {
+            call_graph.record(addr, &instruction, self.index);
+        }
+        if let Some(JsonTrace(ref mut writer)) = self.trace_json {
+            let entry = disasm::TraceEntry::new(addr, &instruction, *self.registers);
+            serde_json::to_writer(&mut *writer, &entry).wrap_err("write json trace")?;
+            writeln!(writer).wrap_err("write json trace")?;
+        }
+
+        Ok(Step {
+            addr,
+            instruction,
+            outcome,
+        })
+    }
+
+    pub fn step(&mut self) -> color_eyre::Result<Outcome> {
+        Ok(self.step_once()?.outcome)
+    }
+
+    pub fn step_nonblocking(&mut self) -> color_eyre::Result<StepOutcome> {
+        if self.stdin.is_empty() {
+            if let Ok(Instruction::In(_)) = decode_at(&self.mem, self.index) {
+                return Ok(StepOutcome::NeedInput);
+            }
+        }
+
+        Ok(match self.step()? {
+            Outcome::Continue => StepOutcome::Continue,
+            Outcome::Halt => StepOutcome::Halt,
+        })
+    }
+
+    pub fn take_output(&mut self) -> Vec<u8> {
+        match self.output {
+            Output::Capture(ref mut captured) => std::mem::take(captured),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn run_until_output(&mut self, needle: &str, budget: u64) -> color_eyre::Result<bool> {
+        let mut seen = VecDeque::with_capacity(needle.len() + 1);
+        for _ in 0..budget {
+            let step = self.step_once()?;
+            if let Instruction::Out(value) = step.instruction {
+                seen.push_back(self.eval_value(step.addr, value)?.0 as u8);
+                if seen.len() > needle.len() {
+                    seen.pop_front();
+                }
+                if seen.iter().eq(needle.as_bytes()) {
+                    self.flush_output()?;
+                    return Ok(true);
+                }
+            }
+            if step.outcome == Outcome::Halt {
+                return Ok(false);
+            }
+        }
+
+        Err(color_eyre::eyre::eyre!(
+            "{needle:?} did not appear within {budget} instructions"
+        ))
+    }
+
+    fn run_until_halt(&mut self) -> color_eyre::Result<()> {
+        // `cycles` is restored from savestates, so the budget counts from here.
+        let start = self.cycles;
+        loop {
+            if let Some(max) = self.max_instructions {
+                if self.cycles - start >= max {
+                    return Err(color_eyre::eyre::eyre!(
+                        "instruction budget of {max} exceeded at {:#06x}",
+                        self.index
+                    ));
+                }
+            }
+            let interrupted = debugger::INTERRUPTED.load(Ordering::Relaxed);
+            if (self.should_pause() || interrupted) && self.debug_prompt()? == Outcome::Halt {
+                return Ok(());
+            }
+            if self.step_once()?.outcome == Outcome::Halt {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn run(&mut self) -> color_eyre::Result<()> {
+        let result = self.run_until_halt();
+        self.flush_output()?;
+        if let Some(ref mut trace) = self.trace {
+            trace.writer.flush().wrap_err("flush trace")?;
+        }
+        if let Some(JsonTrace(ref mut writer)) = self.trace_json {
+            writer.flush().wrap_err("flush json trace")?;
+        }
+        result
+    }
+}
+
+pub mod analysis;
+pub mod asm;
+pub mod coins;
+pub mod debugger;
+pub mod disasm;
+pub mod grid;
+pub mod labels;
+pub mod map;
+pub mod routine;
+pub mod stats;
+pub mod transcript;
+
+#[cfg(test)]
+mod arithmetic;
+
+#[cfg(test)]
+mod faults;
+
+#[cfg(test)]
+mod headless;
+
+#[cfg(test)]
+mod test_util;
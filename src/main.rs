@@ -1,633 +1,386 @@
-use std::{collections::VecDeque, fs::File, io::Write};
-
-use color_eyre::eyre::{Context, ContextCompat};
-
-#[derive(Debug, Clone, Copy)]
-enum Instruction {
-    Halt,
-    Set(Register, Literal),
-    Push(Literal),
-    Pop(Location),
-    Eq(Location, Literal, Literal),
-    Gt(Location, Literal, Literal),
-    Jmp(Address),
-    Jt(Literal, Address),
-    Jf(Literal, Address),
-    Add(Location, Literal, Literal),
-    Mult(Location, Literal, Literal),
-    Mod(Location, Literal, Literal),
-    And(Location, Literal, Literal),
-    Or(Location, Literal, Literal),
-    Not(Location, Literal),
-    Rmem(Location, Address),
-    Wmem(Address, Literal),
-    Call(Address),
-    Ret,
-    Out(Literal),
-    In(Location),
-    Noop,
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, IsTerminal, Write},
+    ops::Range,
+    path::PathBuf,
+    sync::atomic::Ordering,
+    time::Instant,
+};
+
+use clap::Parser;
+use color_eyre::eyre::Context;
+use synacor::{
+    asm, debugger::INTERRUPTED, disasm, parse_number, routine, stats::RunStats, transcript, Input,
+    JsonTrace, Machine, Output, Trace, VmError,
+};
+
+const HALT_REPORT_HISTORY: usize = 8;
+
+const SELFTEST_PASSED: &str = "self-test complete, all tests pass";
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    #[arg(default_value = "challenge.bin")]
+    program: PathBuf,
+    #[arg(long)]
+    logfile: Option<PathBuf>,
+    #[arg(long)]
+    load: Option<PathBuf>,
+    #[arg(long)]
+    script: Option<PathBuf>,
+    #[arg(long)]
+    record: Option<PathBuf>,
+    #[arg(long)]
+    labels: Option<PathBuf>,
+    #[arg(long, num_args = 0..=1, default_missing_value = "-")]
+    trace: Option<PathBuf>,
+    #[arg(long, requires = "trace", value_parser = parse_range)]
+    trace_range: Option<Range<usize>>,
+    #[arg(long)]
+    trace_coalesce: bool,
+    #[arg(long, default_value = "states")]
+    state_dir: PathBuf,
+    #[arg(long, value_parser = parse_r7)]
+    teleporter: Option<u16>,
+    #[arg(long)]
+    compare_output: Option<PathBuf>,
+    #[arg(long)]
+    compare_lenient: bool,
+    #[arg(long)]
+    solve_teleporter: bool,
+    #[arg(long)]
+    no_cache: bool,
+    #[arg(long)]
+    validate: bool,
+    #[arg(long)]
+    selftest: bool,
+    #[arg(long)]
+    profile_output: Option<PathBuf>,
+    #[arg(long)]
+    dump_final_mem: Option<PathBuf>,
+    #[arg(long, num_args = 2, value_names = ["INPUT", "TRANSCRIPT"])]
+    replay_until_diverge: Option<Vec<PathBuf>>,
+    #[arg(long, conflicts_with = "input_fd")]
+    input_path: Option<PathBuf>,
+    #[arg(long)]
+    input_fd: Option<u32>,
+    #[arg(long)]
+    dump_program_json: bool,
+    #[arg(long)]
+    strict: bool,
+    #[arg(long)]
+    halt_on_eof: bool,
+    #[arg(long)]
+    inspect: bool,
+    #[arg(long)]
+    no_game_aliases: bool,
+    #[arg(long)]
+    trace_out: bool,
+    #[arg(long)]
+    watch_stdin_ahead: bool,
+    #[arg(long)]
+    disassemble: bool,
+    #[arg(long, default_value_t = 0)]
+    history: usize,
+    #[arg(long)]
+    stats: bool,
+    #[arg(long)]
+    decode_cache: bool,
+    #[arg(long)]
+    assemble: Option<PathBuf>,
+    #[arg(long)]
+    redo_command: Option<String>,
+    #[arg(long)]
+    input_echo: bool,
+    #[arg(long)]
+    max_instructions: Option<u64>,
+    #[arg(long)]
+    raw_input: bool,
+    #[arg(long)]
+    trace_json: Option<PathBuf>,
+    #[arg(long)]
+    headless: bool,
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Register(usize);
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
 
-impl Register {
-    fn new(register: u16) -> color_eyre::Result<Self> {
-        if (32768..=32775).contains(&register) {
-            Ok(Self(register as usize - 32768))
-        } else {
-            Err(color_eyre::eyre::eyre!("got weird register: {register}"))
+impl ColorChoice {
+    fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
         }
     }
 }
 
-impl std::fmt::Display for Register {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "r{}", self.0)
-    }
-}
+fn parse_range(text: &str) -> Result<Range<usize>, String> {
+    let (start, end) = text
+        .split_once(':')
+        .ok_or_else(|| format!("expected <start>:<end>, got {text}"))?;
+    let start = parse_number(start).map_err(|err| err.to_string())?;
+    let end = parse_number(end).map_err(|err| err.to_string())?;
 
-#[derive(Debug, Clone, Copy)]
-enum Value {
-    Literal(Literal),
-    LiteralAtRegister(Register),
+    Ok(start as usize..end as usize)
 }
 
-impl Value {
-    fn new(value: u16) -> color_eyre::Result<Self> {
-        match value {
-            0..=32767 => Ok(Value::Literal(Literal(value))),
-            32768..=32775 => Ok(Value::LiteralAtRegister(Register(value as usize - 32768))),
-            _ => Err(color_eyre::eyre::eyre!("got weird value: {value}")),
-        }
-    }
+fn parse_r7(text: &str) -> Result<u16, String> {
+    parse_number(text).map_err(|err| err.to_string())
 }
 
-impl std::fmt::Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Value::Literal(literal) => write!(f, "{literal}"),
-            Value::LiteralAtRegister(register) => write!(f, "{register}"),
-        }
-    }
-}
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct Literal(u16);
+    let args = Args::parse();
 
-impl Literal {
-    fn new(literal: u16) -> color_eyre::Result<Self> {
-        if (0..=32767).contains(&literal) {
-            Ok(Self(literal))
-        } else {
-            Err(color_eyre::eyre::eyre!("got weird literal: {literal}"))
+    if args.solve_teleporter {
+        let params = routine::RoutineParams::default();
+        let cache = args.state_dir.join(routine::CACHE_FILE);
+        let cache = (!args.no_cache).then_some(cache.as_path());
+        match routine::find_magic_r7(params, cache)? {
+            Some(r7) => println!("r7 = {r7:#x}"),
+            None => println!("no r7 value satisfies the teleporter routine"),
         }
+        return Ok(());
     }
-}
 
-impl std::fmt::Display for Literal {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:#x}", self.0)
+    if let Some(path) = &args.assemble {
+        let source = std::fs::read_to_string(&args.program).wrap_err("read listing")?;
+        let bytes = asm::assemble(&source)?
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        std::fs::write(path, bytes).wrap_err("write assembled program")?;
+        return Ok(());
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-enum Location {
-    Address(Address),
-    Register(Register),
-}
-
-impl Location {
-    fn new(location: u16) -> color_eyre::Result<Self> {
-        match location {
-            0..=32767 => Ok(Location::Address(Address(location as usize))),
-            32768..=32775 => Ok(Location::Register(Register(location as usize - 32768))),
-            _ => Err(color_eyre::eyre::eyre!("got weird location: {location}")),
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
         }
-    }
-}
-
-impl std::fmt::Display for Location {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Location::Address(address) => write!(f, "{address}"),
-            Location::Register(register) => write!(f, "{register}"),
+        eprintln!("\ninterrupted, pausing at the next instruction (Ctrl-C again to quit)");
+    })
+    .wrap_err("install ctrl-c handler")?;
+
+    let program = std::fs::read(&args.program).wrap_err("read input file")?;
+    if args.selftest {
+        let output = Machine::run_headless(&program, "")?;
+        if !output.contains(SELFTEST_PASSED) {
+            eprintln!("self-test did not pass:\n{output}");
+            std::process::exit(1);
         }
+        println!("self-test passed");
+        return Ok(());
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-struct Address(usize);
+    let mut machine = Machine::new(&program);
+    if args.headless {
+        machine.output = Output::Writer(Box::new(std::io::sink()));
+        machine.input = Input::Reader(Box::new(std::io::empty()));
+        machine.halt_on_eof = true;
+        machine.game_aliases = None;
+        machine.max_instructions = args.max_instructions;
+        if args.decode_cache {
+            machine.enable_decode_cache();
+        }
 
-impl Address {
-    fn new(address: u16) -> color_eyre::Result<Self> {
-        if (0..=32767).contains(&address) {
-            Ok(Self(address as usize))
+        let start = Instant::now();
+        machine.run()?;
+        println!(
+            "{} instructions in {:.3?}",
+            machine.cycles(),
+            start.elapsed()
+        );
+        return Ok(());
+    }
+    machine.eof_redo_command = args.redo_command.clone();
+    if let Some(path) = &args.load {
+        let state = std::fs::read(path).wrap_err("load state")?;
+        machine.load_state(&state)?;
+        machine.redo_stdin();
+    }
+    if let Some(path) = &args.script {
+        let script = std::fs::read(path).wrap_err("read script")?;
+        machine.feed_input(&script);
+    }
+    if let Some(path) = &args.record {
+        machine.set_recording(path)?;
+    }
+    if let Some(path) = &args.trace {
+        let to_stderr = path.as_os_str() == "-";
+        let writer: Box<dyn Write> = if to_stderr {
+            Box::new(BufWriter::new(std::io::stderr()))
         } else {
-            Err(color_eyre::eyre::eyre!("got weird address: {address}"))
-        }
+            Box::new(BufWriter::new(
+                File::create(path).wrap_err("create trace file")?,
+            ))
+        };
+        machine.trace = Some(Trace {
+            writer,
+            range: args.trace_range.clone(),
+            color: to_stderr && args.color.enabled(std::io::stderr().is_terminal()),
+        });
     }
-}
-
-impl std::fmt::Display for Address {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:#04x}", self.0)
+    if let Some(path) = &args.trace_json {
+        let file = File::create(path).wrap_err("create json trace file")?;
+        machine.trace_json = Some(JsonTrace(Box::new(BufWriter::new(file))));
     }
-}
-
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-struct Machine {
-    mem: Vec<u16>,
-    registers: Box<[u16; 8]>,
-    stack: Vec<u16>,
-    index: usize,
-    stdin: VecDeque<u8>,
-    #[serde(skip)]
-    logger: Option<File>,
-}
-
-impl Machine {
-    fn new(program: &[u8]) -> Self {
-        let mut mem = vec![0; 1 << 15];
-        for (i, val) in program
-            .chunks_exact(2)
-            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
-            .enumerate()
-        {
-            mem[i] = val;
-        }
-
-        Self {
-            mem,
-            registers: Box::new([0; 8]),
-            stack: Vec::new(),
-            index: 0,
-            stdin: VecDeque::new(),
-            logger: None,
-        }
+    if let Some(path) = &args.labels {
+        let text = std::fs::read_to_string(path).wrap_err("read labels")?;
+        machine.labels.load(&text)?;
     }
-
-    fn read_mem(&mut self) -> u16 {
-        let mem = self.mem[self.index];
-        self.index += 1;
-        mem
+    if let Some(path) = &args.logfile {
+        machine.set_logfile(path)?;
     }
-
-    fn read_register(&mut self) -> color_eyre::Result<Register> {
-        let register = self.read_mem();
-        Register::new(register)
+    machine.strict = args.strict;
+    machine.color = args.color.enabled(std::io::stdout().is_terminal());
+    machine.max_instructions = args.max_instructions;
+    machine.trace_coalesce = args.trace_coalesce;
+    machine.state_dir = args.state_dir.clone();
+    machine.teleporter_bypass = args.teleporter;
+    machine.halt_on_eof = args.halt_on_eof;
+    machine.trace_out = args.trace_out;
+    machine.input_echo = args.input_echo;
+    machine.raw_input = args.raw_input;
+    machine.watch_stdin_ahead = args.watch_stdin_ahead;
+    if args.decode_cache {
+        machine.enable_decode_cache();
     }
-
-    fn read_value(&mut self) -> color_eyre::Result<Value> {
-        let value = self.read_mem();
-        Value::new(value)
+    if args.no_game_aliases {
+        machine.game_aliases = None;
     }
-
-    fn read_location(&mut self) -> color_eyre::Result<Location> {
-        let location = self.read_mem();
-        Location::new(location)
+    machine.debugger.history_len = args.history;
+    if args.inspect {
+        machine.debugger.history_len = machine.debugger.history_len.max(HALT_REPORT_HISTORY);
     }
 
-    fn read_instruction(&mut self) -> color_eyre::Result<Instruction> {
-        if self.index == 0x178b && self.registers[7] == 1 {
-            println!("hacking...");
-            self.mem[0x178b] = 18;
-            self.registers[0] = 0x6;
-            self.registers[7] = 0x6486;
+    if args.validate {
+        let errors = machine.reachable_decode_errors();
+        println!("{} decode errors in reachable code", errors.len());
+        for (addr, err) in errors.iter().take(10) {
+            println!("  {addr:#06x}: {err}");
         }
-        let opcode = self.read_mem();
-        Ok(match opcode {
-            0 => {
-                self.maybe_write_to_logger(format_args!("halt"), 1)?;
-
-                Instruction::Halt
-            }
-            1 => {
-                let register = self.read_register()?;
-                let value = self.read_value()?;
-                let literal = self.eval_value(value)?;
-
-                self.maybe_write_to_logger(format_args!("set  {register} {value}"), 3)?;
-
-                Instruction::Set(register, literal)
-            }
-            2 => {
-                let value = self.read_value()?;
-                let literal = self.eval_value(value)?;
-
-                self.maybe_write_to_logger(format_args!("push {value}"), 2)?;
-
-                Instruction::Push(literal)
-            }
-            3 => {
-                let location = self.read_location()?;
-
-                self.maybe_write_to_logger(format_args!("pop  {location}"), 2)?;
-
-                Instruction::Pop(location)
-            }
-            4 => {
-                let location = self.read_location()?;
-                let leftv = self.read_value()?;
-                let left = self.eval_value(leftv)?;
-                let rightv = self.read_value()?;
-                let right = self.eval_value(rightv)?;
-
-                self.maybe_write_to_logger(format_args!("eq   {location} {leftv} {rightv}"), 4)?;
-
-                Instruction::Eq(location, left, right)
-            }
-            5 => {
-                let location = self.read_location()?;
-                let leftv = self.read_value()?;
-                let left = self.eval_value(leftv)?;
-                let rightv = self.read_value()?;
-                let right = self.eval_value(rightv)?;
-
-                self.maybe_write_to_logger(format_args!("gt   {location} {leftv} {rightv}"), 4)?;
-
-                Instruction::Gt(location, left, right)
-            }
-            6 => {
-                let location = self.read_location()?;
-                let address = self.eval_location(location)?;
-
-                self.maybe_write_to_logger(format_args!("jmp  {location}"), 2)?;
-
-                Instruction::Jmp(address)
-            }
-            7 => {
-                let value = self.read_value()?;
-                let literal = self.eval_value(value)?;
-                let location = self.read_location()?;
-                let address = self.eval_location(location)?;
-
-                self.maybe_write_to_logger(format_args!("jt   {value} {location}"), 3)?;
-
-                Instruction::Jt(literal, address)
-            }
-            8 => {
-                let value = self.read_value()?;
-                let literal = self.eval_value(value)?;
-                let location = self.read_location()?;
-                let address = self.eval_location(location)?;
-
-                self.maybe_write_to_logger(format_args!("jf   {value} {location}"), 3)?;
-
-                Instruction::Jf(literal, address)
-            }
-            9 => {
-                let location = self.read_location()?;
-                let leftv = self.read_value()?;
-                let left = self.eval_value(leftv)?;
-                let rightv = self.read_value()?;
-                let right = self.eval_value(rightv)?;
-
-                self.maybe_write_to_logger(format_args!("add  {location} {leftv} {rightv}"), 4)?;
-
-                Instruction::Add(location, left, right)
-            }
-            10 => {
-                let location = self.read_location()?;
-                let leftv = self.read_value()?;
-                let left = self.eval_value(leftv)?;
-                let rightv = self.read_value()?;
-                let right = self.eval_value(rightv)?;
-
-                self.maybe_write_to_logger(format_args!("mult {location} {leftv} {rightv}"), 4)?;
-
-                Instruction::Mult(location, left, right)
-            }
-            11 => {
-                let location = self.read_location()?;
-                let leftv = self.read_value()?;
-                let left = self.eval_value(leftv)?;
-                let rightv = self.read_value()?;
-                let right = self.eval_value(rightv)?;
-
-                self.maybe_write_to_logger(format_args!("mod  {location} {leftv} {rightv}"), 4)?;
-
-                Instruction::Mod(location, left, right)
-            }
-            12 => {
-                let location = self.read_location()?;
-                let leftv = self.read_value()?;
-                let left = self.eval_value(leftv)?;
-                let rightv = self.read_value()?;
-                let right = self.eval_value(rightv)?;
-
-                self.maybe_write_to_logger(format_args!("and  {location} {leftv} {rightv}"), 4)?;
-
-                Instruction::And(location, left, right)
-            }
-            13 => {
-                let location = self.read_location()?;
-                let leftv = self.read_value()?;
-                let left = self.eval_value(leftv)?;
-                let rightv = self.read_value()?;
-                let right = self.eval_value(rightv)?;
-
-                self.maybe_write_to_logger(format_args!("or   {location} {leftv} {rightv}"), 4)?;
-
-                Instruction::Or(location, left, right)
-            }
-            14 => {
-                let location = self.read_location()?;
-                let operandv = self.read_value()?;
-                let operand = self.eval_value(operandv)?;
-
-                self.maybe_write_to_logger(format_args!("not  {location} {operandv}"), 3)?;
-
-                Instruction::Not(location, operand)
-            }
-            15 => {
-                let dest = self.read_location()?;
-                let srcl = self.read_location()?;
-                let src = self.eval_location(srcl)?;
-
-                self.maybe_write_to_logger(format_args!("rmem {dest} {srcl}"), 3)?;
-
-                Instruction::Rmem(dest, src)
-            }
-            16 => {
-                let destl = self.read_location()?;
-                let dest = self.eval_location(destl)?;
-                let srcv = self.read_value()?;
-                let src = self.eval_value(srcv)?;
-
-                self.maybe_write_to_logger(format_args!("wmem {destl} {srcv}"), 3)?;
-
-                Instruction::Wmem(dest, src)
-            }
-            17 => {
-                let location = self.read_location()?;
-                let address = self.eval_location(location)?;
-
-                self.maybe_write_to_logger(format_args!("call {location}"), 2)?;
-
-                Instruction::Call(address)
-            }
-            18 => {
-                self.maybe_write_to_logger(format_args!("ret "), 1)?;
+        if errors.len() > 10 {
+            println!("  ...");
+        }
+        if !errors.is_empty() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-                Instruction::Ret
-            }
-            19 => {
-                let value = self.read_value()?;
-                let literal = self.eval_value(value)?;
+    if args.disassemble {
+        for (addr, text) in machine.disassemble_range(0, machine.program().len()) {
+            println!("{addr:#06x}    {text}");
+        }
+        return Ok(());
+    }
 
-                self.maybe_write_to_logger(format_args!("out  {value}"), 2)?;
+    if args.dump_program_json {
+        let entries = disasm::program_entries(machine.program());
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&entries).wrap_err("serialize program")?
+        );
+        return Ok(());
+    }
 
-                Instruction::Out(literal)
-            }
-            20 => {
-                let dest = self.read_location()?;
+    let input_path = args
+        .input_fd
+        .map(|fd| PathBuf::from(format!("/dev/fd/{fd}")))
+        .or(args.input_path);
+    if let Some(path) = &input_path {
+        let input = File::open(path).wrap_err("open input path")?;
+        machine.input = Input::Reader(Box::new(BufReader::new(input)));
+    }
 
-                self.maybe_write_to_logger(format_args!("in   {dest}"), 2)?;
+    if let Some([input, transcript]) = args.replay_until_diverge.as_deref() {
+        let input = File::open(input).wrap_err("open recorded input")?;
+        let expected = std::fs::read(transcript).wrap_err("read recorded transcript")?;
+        machine.input = Input::Reader(Box::new(BufReader::new(input)));
+        machine.output = Output::Expect(transcript::Expected::new(expected));
 
-                Instruction::In(dest)
+        if let Err(err) = machine.run() {
+            if let Some(divergence) = err.downcast_ref::<transcript::Divergence>() {
+                eprintln!("{divergence}");
+                std::process::exit(1);
             }
-            21 => {
-                self.maybe_write_to_logger(format_args!("noop"), 1)?;
-
-                Instruction::Noop
+            if err.downcast_ref::<VmError>() != Some(&VmError::InputEof) {
+                return Err(err);
             }
-            _ => return Err(color_eyre::eyre::eyre!("got weird opcode: {opcode}")),
-        })
-    }
-
-    fn redo_stdin(&mut self) {
-        self.index -= 2;
-        for ch in b"look\n".iter().rev().copied() {
-            self.stdin.push_front(ch);
         }
-    }
 
-    fn read_stdin(&mut self) -> color_eyre::Result<Option<u16>> {
-        match self.stdin.pop_front() {
-            Some(raw) => Ok(Some(raw as u16)),
-            None => {
-                let mut line = String::new();
-
-                let bytes_read = std::io::stdin()
-                    .read_line(&mut line)
-                    .wrap_err("read from stdin")?;
-                if bytes_read == 0 {
-                    return Err(color_eyre::eyre::eyre!("stdin has reached EOF"));
-                }
-
-                if line.starts_with("savestate") {
-                    let (_, filename) = line.split_once(' ').wrap_err("get filename")?;
-                    let filename = filename.trim();
-                    std::fs::write(
-                        filename,
-                        serde_json::to_string(self).wrap_err("serialize state")?,
-                    )
-                    .wrap_err("save state")?;
-
-                    std::process::exit(0);
-                } else if line.starts_with("loadstate") {
-                    let (_, filename) = line.split_once(' ').wrap_err("get filename")?;
-                    let filename = filename.trim();
-                    let deserialized = serde_json::from_str(
-                        &std::fs::read_to_string(filename).wrap_err("load state")?,
-                    )
-                    .wrap_err("deserialize state")?;
-                    *self = deserialized;
-
-                    Ok(None)
-                } else if line.starts_with("dumpregs") {
-                    for (register, val) in self.registers.iter().copied().enumerate() {
-                        println!("Register {register} = {val:#x}");
-                    }
-
-                    Ok(None)
-                } else if line.starts_with("dumpreg") {
-                    let (_, reg) = line.split_once(' ').wrap_err("get register")?;
-                    let reg = reg
-                        .trim()
-                        .parse::<usize>()
-                        .wrap_err("parse register into usize")?;
-                    println!("Register {reg} = {:#x}", self.registers[reg]);
-
-                    Ok(None)
-                } else if line.starts_with("setreg") {
-                    let mut iter = line.trim().splitn(3, ' ');
-                    let _ = iter
-                        .next()
-                        .ok_or_else(|| color_eyre::eyre::eyre!("something sketchy's happening"))?;
-                    let reg = iter
-                        .next()
-                        .ok_or_else(|| color_eyre::eyre::eyre!("get register"))?
-                        .parse::<usize>()
-                        .wrap_err("parse register into usize")?;
-                    let val = iter
-                        .next()
-                        .ok_or_else(|| color_eyre::eyre::eyre!("get value"))?
-                        .parse::<u16>()
-                        .wrap_err("parse value into u16")?;
-                    self.registers[reg] = val;
-
-                    Ok(None)
-                } else if line.starts_with("logfile") {
-                    let (_, filename) = line.split_once(' ').wrap_err("get filename")?;
-                    let filename = filename.trim();
-                    let file = File::create(filename).wrap_err("create logfile")?;
-                    self.logger = Some(file);
-
-                    Ok(None)
-                } else if line.starts_with("nolog") {
-                    self.logger = None;
-
-                    Ok(None)
-                } else {
-                    self.stdin.extend(
-                        line.chars()
-                            .filter_map(|ch| (ch != '\r').then_some(ch as u8)),
-                    );
-                    self.read_stdin()
-                }
-            }
+        let Output::Expect(expected) = &machine.output else {
+            unreachable!("output is always checked when replaying");
+        };
+        if let Err(divergence) = expected.finish(machine.cycles()) {
+            eprintln!("{divergence}");
+            std::process::exit(1);
         }
-    }
 
-    fn eval_register(&self, register: Register) -> u16 {
-        self.registers[register.0]
+        println!("replay matches the recorded transcript");
+        return Ok(());
     }
 
-    fn eval_location(&self, location: Location) -> color_eyre::Result<Address> {
-        match location {
-            Location::Address(address) => Ok(address),
-            Location::Register(register) => Address::new(self.eval_register(register)),
+    let golden = match args.compare_output {
+        Some(golden) => {
+            let expected = std::fs::read(&golden).wrap_err("read golden transcript")?;
+            machine.output = Output::Capture(Vec::new());
+            Some((golden, expected))
         }
+        None => None,
+    };
+    if args.profile_output.is_some() || args.stats {
+        machine.stats = Some(RunStats::default());
     }
 
-    fn eval_value(&self, value: Value) -> color_eyre::Result<Literal> {
-        match value {
-            Value::Literal(literal) => Ok(literal),
-            Value::LiteralAtRegister(register) => Literal::new(self.eval_register(register)),
-        }
-    }
+    let result = machine.run();
 
-    fn write_to_location(&mut self, location: Location, raw: u16) {
-        match location {
-            Location::Address(address) => self.mem[address.0] = raw,
-            Location::Register(register) => self.registers[register.0] = raw,
-        }
+    if args.inspect {
+        machine.print_halt_report();
     }
 
-    fn maybe_write_to_logger(
-        &mut self,
-        args: std::fmt::Arguments,
-        index_offset: usize,
-    ) -> color_eyre::Result<()> {
-        if let Some(ref mut logger) = self.logger {
-            writeln!(logger, "{:#06x}    {}", self.index - index_offset, args)
-                .wrap_err("write to logger")?;
-        }
-
-        Ok(())
+    if let (true, Some(stats)) = (args.stats, &machine.stats) {
+        stats.print_summary();
     }
 
-    fn write_stdout(&mut self, raw: u16) {
-        print!("{}", raw as u8 as char)
+    if let (Some(path), Some(stats)) = (&args.profile_output, &machine.stats) {
+        std::fs::write(
+            path,
+            serde_json::to_string_pretty(stats).wrap_err("serialize stats")?,
+        )
+        .wrap_err("write profile output")?;
     }
 
-    fn pop_stack(&mut self) -> color_eyre::Result<u16> {
-        self.stack.pop().wrap_err("pop stack")
+    if let (Some(path), Ok(())) = (&args.dump_final_mem, &result) {
+        std::fs::write(path, machine.mem_bytes()).wrap_err("write final memory dump")?;
     }
 
-    fn run(&mut self) -> color_eyre::Result<()> {
-        loop {
-            match self.read_instruction()? {
-                Instruction::Halt => return Ok(()),
-                Instruction::Set(register, literal) => self.registers[register.0] = literal.0,
-                Instruction::Push(literal) => self.stack.push(literal.0),
-                Instruction::Pop(location) => {
-                    let raw = self.pop_stack()?;
-                    self.write_to_location(location, raw)
-                }
-                Instruction::Eq(location, left, right) => {
-                    self.write_to_location(location, if left == right { 1 } else { 0 })
-                }
-                Instruction::Gt(location, left, right) => {
-                    self.write_to_location(location, if left > right { 1 } else { 0 })
-                }
-                Instruction::Jmp(address) => self.index = address.0,
-                Instruction::Jt(literal, address) => {
-                    if literal.0 != 0 {
-                        self.index = address.0
-                    }
-                }
-                Instruction::Jf(literal, address) => {
-                    if literal.0 == 0 {
-                        self.index = address.0
-                    }
-                }
-                Instruction::Add(dest, left, right) => {
-                    let sum = (left.0 + right.0) % 32768;
-                    self.write_to_location(dest, sum)
-                }
-                Instruction::Mult(dest, left, right) => {
-                    let product = ((left.0 as u32 * right.0 as u32) % 32768) as u16;
-                    self.write_to_location(dest, product)
-                }
-                Instruction::Mod(dest, left, right) => {
-                    let rem = left.0 % right.0;
-                    self.write_to_location(dest, rem)
-                }
-                Instruction::And(dest, left, right) => {
-                    let anded = left.0 & right.0;
-                    self.write_to_location(dest, anded)
-                }
-                Instruction::Or(dest, left, right) => {
-                    let ored = left.0 | right.0;
-                    self.write_to_location(dest, ored)
-                }
-                Instruction::Not(dest, operand) => {
-                    let noted = !operand.0;
-                    let noted = noted & 0x7fff;
-                    self.write_to_location(dest, noted)
-                }
-                Instruction::Rmem(dest, src) => {
-                    let mem = self.mem[src.0];
-                    self.write_to_location(dest, mem)
-                }
-                Instruction::Wmem(dest, src) => self.mem[dest.0] = src.0,
-                Instruction::Call(address) => {
-                    self.stack.push(self.index as u16);
-                    self.index = address.0
-                }
-                Instruction::Ret => {
-                    let dest = self.pop_stack()? as usize;
-                    self.index = dest
-                }
-                Instruction::Out(literal) => self.write_stdout(literal.0),
-                Instruction::In(location) => {
-                    let raw = self.read_stdin()?;
-                    match raw {
-                        Some(raw) => self.write_to_location(location, raw),
-                        None => self.redo_stdin(),
-                    }
-                }
-                Instruction::Noop => {}
+    if let Some((golden, expected)) = golden {
+        if let Err(err) = result {
+            if err.downcast_ref::<VmError>() != Some(&VmError::InputEof) {
+                return Err(err);
             }
         }
-    }
-}
 
-fn main() -> color_eyre::Result<()> {
-    color_eyre::install()?;
+        let Output::Capture(actual) = &machine.output else {
+            unreachable!("output is always captured when comparing");
+        };
+        if let Some(mismatch) = transcript::compare(actual, &expected, args.compare_lenient) {
+            eprintln!("output differs from {}: {mismatch}", golden.display());
+            std::process::exit(1);
+        }
 
-    let program = std::fs::read("challenge.bin").wrap_err("read input file")?;
-    let mut machine = Machine::new(&program);
-    machine.run()?;
+        println!("output matches {}", golden.display());
+        return Ok(());
+    }
 
-    Ok(())
+    result
 }
-
-#[cfg(test)]
-mod routine;
-
-#[cfg(test)]
-mod grid;
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct GameMap {
+    pub rooms: BTreeMap<String, Vec<(String, Option<String>)>>,
+    #[serde(skip)]
+    line: String,
+    #[serde(skip)]
+    current: Option<String>,
+    #[serde(skip)]
+    in_exits: bool,
+    #[serde(skip)]
+    moving: Option<(String, String)>,
+}
+
+impl GameMap {
+    pub fn record_output(&mut self, byte: u8) {
+        if byte != b'\n' {
+            self.line.push(byte as char);
+            return;
+        }
+
+        let line = std::mem::take(&mut self.line);
+        if let Some(room) = line
+            .strip_prefix("== ")
+            .and_then(|line| line.strip_suffix(" =="))
+        {
+            self.enter(room.to_string());
+        } else if line.starts_with("There ") && line.contains(" exit") {
+            self.in_exits = true;
+        } else if let (true, Some(exit)) = (self.in_exits, line.strip_prefix("- ")) {
+            if let Some(exits) = self
+                .current
+                .as_ref()
+                .and_then(|room| self.rooms.get_mut(room))
+            {
+                if !exits.iter().any(|(direction, _)| direction == exit) {
+                    exits.push((exit.to_string(), None));
+                }
+            }
+        } else {
+            self.in_exits = false;
+        }
+    }
+
+    pub fn record_input(&mut self, line: &str) {
+        let line = line.trim();
+        let direction = line.strip_prefix("go ").unwrap_or(line).trim();
+        let Some(ref room) = self.current else {
+            return;
+        };
+        if self.rooms[room].iter().any(|(exit, _)| exit == direction) {
+            self.moving = Some((room.clone(), direction.to_string()));
+        }
+    }
+
+    fn enter(&mut self, room: String) {
+        if let Some((from, direction)) = self.moving.take() {
+            if let Some(exit) = self
+                .rooms
+                .get_mut(&from)
+                .and_then(|exits| exits.iter_mut().find(|(exit, _)| *exit == direction))
+            {
+                exit.1 = Some(room.clone());
+            }
+        }
+        self.rooms.entry(room.clone()).or_default();
+        self.current = Some(room);
+        self.in_exits = false;
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph map {\n");
+        for (room, exits) in &self.rooms {
+            dot.push_str(&format!("    {room:?};\n"));
+            for (direction, target) in exits {
+                if let Some(target) = target {
+                    dot.push_str(&format!(
+                        "    {room:?} -> {target:?} [label={direction:?}];\n"
+                    ));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[test]
+fn records_room_connections() {
+    let mut map = GameMap::default();
+    let output =
+        |map: &mut GameMap, text: &str| text.bytes().for_each(|byte| map.record_output(byte));
+    output(&mut map, "\n== Foothills ==\nA mountain.\n\nThere are 2 exits:\n- doorway\n- south\n\nWhat do you do?\n");
+    map.record_input("doorway\n");
+    output(
+        &mut map,
+        "\n== Dark cave ==\nIt is dark.\n\nThere is 1 exit:\n- south\n\n",
+    );
+    map.record_input("go south\n");
+    output(&mut map, "\n== Foothills ==\n");
+
+    assert_eq!(
+        map.rooms["Foothills"],
+        [
+            ("doorway".to_string(), Some("Dark cave".to_string())),
+            ("south".to_string(), None)
+        ]
+    );
+    assert_eq!(
+        map.rooms["Dark cave"],
+        [("south".to_string(), Some("Foothills".to_string()))]
+    );
+    assert!(map
+        .to_dot()
+        .contains("\"Foothills\" -> \"Dark cave\" [label=\"doorway\"];"));
+}
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
+use color_eyre::eyre::Context;
 use rayon::{
     prelude::{IntoParallelIterator, ParallelIterator},
     ThreadPoolBuilder,
@@ -7,20 +8,43 @@ use rayon::{
 
 type Regs = (u16, u16);
 
-struct Search {
+pub const CACHE_FILE: &str = "teleporter_cache.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RoutineParams {
+    pub regs: Regs,
+    pub target: u16,
+}
+
+impl Default for RoutineParams {
+    fn default() -> Self {
+        Self {
+            regs: (4, 1),
+            target: 6,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct CachedResult {
+    params: RoutineParams,
+    r7: u16,
+}
+
+pub struct Search {
     r7: u16,
     memo: HashMap<Regs, Regs>,
 }
 
 impl Search {
-    fn new(r7: u16) -> Self {
+    pub fn new(r7: u16) -> Self {
         Self {
             r7,
             memo: HashMap::new(),
         }
     }
 
-    fn find(&mut self, regs: Regs) -> Regs {
+    pub fn find(&mut self, regs: Regs) -> Regs {
         if let Some(&ret) = self.memo.get(&regs) {
             return ret;
         }
@@ -44,28 +68,55 @@ impl Search {
     }
 }
 
-#[test]
-fn find_magic_value() {
-    ThreadPoolBuilder::new()
+fn search(params: RoutineParams) -> color_eyre::Result<Option<u16>> {
+    let pool = ThreadPoolBuilder::new()
         .stack_size(24 * 1024 * 1024)
-        .build_global()
-        .unwrap();
-
-    let magic_number = (1..(1 << 15))
-        .into_par_iter()
-        .filter_map(|r7| {
-            if r7 % 1000 == 0 {
-                println!("working on r7 = {r7}");
-            }
+        .build()
+        .wrap_err("build search thread pool")?;
+
+    Ok(pool.install(|| {
+        (1..(1 << 15)).into_par_iter().find_any(|&r7| {
             let mut search = Search::new(r7);
-            let result = search.find((4, 1));
-            if result.0 == 6 {
-                Some(r7)
-            } else {
-                None
-            }
+            search.find(params.regs).0 == params.target
         })
-        .find_any(|_| true)
-        .unwrap();
+    }))
+}
+
+pub fn solve() -> color_eyre::Result<Option<u16>> {
+    search(RoutineParams::default())
+}
+
+fn read_cache(path: &Path, params: RoutineParams) -> Option<u16> {
+    let cached = std::fs::read_to_string(path).ok()?;
+    let cached: CachedResult = serde_json::from_str(&cached).ok()?;
+    (cached.params == params).then_some(cached.r7)
+}
+
+pub fn find_magic_r7(
+    params: RoutineParams,
+    cache: Option<&Path>,
+) -> color_eyre::Result<Option<u16>> {
+    if let Some(r7) = cache.and_then(|path| read_cache(path, params)) {
+        return Ok(Some(r7));
+    }
+
+    let r7 = search(params)?;
+    if let (Some(r7), Some(path)) = (r7, cache) {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).wrap_err("create teleporter cache directory")?;
+        }
+        std::fs::write(
+            path,
+            serde_json::to_string(&CachedResult { params, r7 }).wrap_err("serialize cache")?,
+        )
+        .wrap_err("write teleporter cache")?;
+    }
+
+    Ok(r7)
+}
+
+#[test]
+fn find_magic_value() {
+    let magic_number = solve().unwrap().unwrap();
     println!("{magic_number}");
 }
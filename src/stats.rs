@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Instruction, MEM_SIZE};
+
+#[derive(Debug, serde::Serialize)]
+pub struct RunStats {
+    pub cycles: u64,
+    pub opcodes: BTreeMap<&'static str, u64>,
+    pub max_call_depth: usize,
+    pub coverage: usize,
+    #[serde(skip)]
+    hits: Vec<u64>,
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        Self {
+            cycles: 0,
+            opcodes: BTreeMap::new(),
+            max_call_depth: 0,
+            coverage: 0,
+            hits: vec![0; MEM_SIZE],
+        }
+    }
+}
+
+impl RunStats {
+    pub fn record(&mut self, addr: usize, instruction: &Instruction, call_depth: usize) {
+        self.cycles += 1;
+        *self.opcodes.entry(instruction.mnemonic()).or_insert(0) += 1;
+        self.max_call_depth = self.max_call_depth.max(call_depth);
+        if let Some(hits) = self.hits.get_mut(addr) {
+            if *hits == 0 {
+                self.coverage += 1;
+            }
+            *hits += 1;
+        }
+    }
+
+    pub fn categories(&self) -> BTreeMap<&'static str, u64> {
+        let mut categories = BTreeMap::new();
+        for (&mnemonic, &count) in &self.opcodes {
+            *categories.entry(category(mnemonic)).or_insert(0) += count;
+        }
+        categories
+    }
+
+    pub fn print_summary(&self) {
+        println!("{} instructions executed", self.cycles);
+        for (category, count) in self.categories() {
+            println!("  {category:<12} {count}");
+        }
+    }
+
+    pub fn hot_addresses(&self) -> Vec<(usize, u64)> {
+        let mut hot = self
+            .hits
+            .iter()
+            .enumerate()
+            .filter(|&(_, &hits)| hits > 0)
+            .map(|(addr, &hits)| (addr, hits))
+            .collect::<Vec<_>>();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot
+    }
+}
+
+fn category(mnemonic: &str) -> &'static str {
+    match mnemonic {
+        "add" | "mult" | "mod" | "and" | "or" | "not" | "eq" | "gt" => "arithmetic",
+        "jmp" | "jt" | "jf" | "call" | "ret" | "halt" | "noop" => "control flow",
+        "set" | "push" | "pop" | "rmem" | "wmem" => "memory",
+        _ => "i/o",
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    pub functions: BTreeMap<usize, u64>,
+    pub callers: BTreeMap<usize, BTreeSet<usize>>,
+    pub unmatched_returns: u64,
+    frames: Vec<(usize, usize)>,
+}
+
+impl CallGraph {
+    pub fn record(&mut self, addr: usize, instruction: &Instruction, next: usize) {
+        match instruction {
+            Instruction::Call(_) => {
+                *self.functions.entry(next).or_insert(0) += 1;
+                self.callers.entry(next).or_default().insert(addr);
+                self.frames.push((addr + instruction.width(), next));
+            }
+            Instruction::Ret => {
+                match self
+                    .frames
+                    .iter()
+                    .rposition(|&(return_addr, _)| return_addr == next)
+                {
+                    Some(frame) => self.frames.truncate(frame),
+                    None => self.unmatched_returns += 1,
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn nested_calls_return_in_order() {
+    let call = Instruction::Call(crate::Location::Register(crate::Register(0)));
+    let mut graph = CallGraph::default();
+    graph.record(0x10, &call, 0x100);
+    graph.record(0x104, &call, 0x200);
+    assert_eq!(graph.frames, [(0x12, 0x100), (0x106, 0x200)]);
+
+    graph.record(0x201, &Instruction::Ret, 0x106);
+    assert_eq!(graph.frames, [(0x12, 0x100)]);
+    graph.record(0x108, &Instruction::Ret, 0x12);
+    assert!(graph.frames.is_empty());
+    assert_eq!(graph.functions, BTreeMap::from([(0x100, 1), (0x200, 1)]));
+    assert_eq!(graph.unmatched_returns, 0);
+}
+
+#[test]
+fn unmatched_ret_leaves_frames_alone() {
+    let call = Instruction::Call(crate::Location::Register(crate::Register(0)));
+    let mut graph = CallGraph::default();
+    graph.record(0x10, &call, 0x100);
+    graph.record(0x101, &Instruction::Ret, 0x500);
+    assert_eq!(graph.frames, [(0x12, 0x100)]);
+    assert_eq!(graph.unmatched_returns, 1);
+
+    graph.record(0x102, &Instruction::Ret, 0x12);
+    assert!(graph.frames.is_empty());
+}
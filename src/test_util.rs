@@ -0,0 +1,9 @@
+use crate::Machine;
+
+pub fn program(words: &[u16]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+pub fn machine(words: &[u16]) -> Machine {
+    Machine::new(&program(words))
+}
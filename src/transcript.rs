@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+
+#[derive(Debug)]
+pub struct Mismatch {
+    offset: usize,
+    line: usize,
+    expected: Option<String>,
+    actual: Option<String>,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "first difference at byte {} (line {})",
+            self.offset, self.line
+        )?;
+        match &self.expected {
+            Some(expected) => writeln!(f, "  expected: {expected:?}")?,
+            None => writeln!(f, "  expected: <end of transcript>")?,
+        }
+        match &self.actual {
+            Some(actual) => write!(f, "  actual:   {actual:?}"),
+            None => write!(f, "  actual:   <end of output>"),
+        }
+    }
+}
+
+const DIAGNOSTIC_LINES: &[&str] = &["hacking..."];
+
+fn normalize(raw: &[u8]) -> Vec<u8> {
+    let text = String::from_utf8_lossy(raw);
+    let mut lines = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !DIAGNOSTIC_LINES.contains(line))
+        .collect::<Vec<_>>();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+
+    lines.join("\n").into_bytes()
+}
+
+fn line_at(text: &[u8], offset: usize) -> Option<String> {
+    if offset >= text.len() {
+        return None;
+    }
+
+    let start = text[..offset]
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    let end = text[offset..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(text.len(), |newline| offset + newline);
+
+    Some(String::from_utf8_lossy(&text[start..end]).into_owned())
+}
+
+pub fn compare(actual: &[u8], expected: &[u8], lenient: bool) -> Option<Mismatch> {
+    let (actual, expected): (Cow<[u8]>, Cow<[u8]>) = if lenient {
+        (normalize(actual).into(), normalize(expected).into())
+    } else {
+        (actual.into(), expected.into())
+    };
+
+    let offset = actual
+        .iter()
+        .zip(expected.iter())
+        .position(|(a, e)| a != e)
+        .or_else(|| (actual.len() != expected.len()).then(|| actual.len().min(expected.len())))?;
+
+    let line = actual[..offset]
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .count()
+        + 1;
+
+    Some(Mismatch {
+        offset,
+        line,
+        expected: line_at(&expected, offset),
+        actual: line_at(&actual, offset),
+    })
+}
+
+#[derive(Debug)]
+pub struct Divergence {
+    cycle: u64,
+    offset: usize,
+    expected: Option<u8>,
+    actual: Option<u8>,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "output diverged at cycle {} (output byte {}): ",
+            self.cycle, self.offset
+        )?;
+        match self.expected {
+            Some(expected) => write!(f, "expected {:?}, ", expected as char)?,
+            None => write!(f, "expected <end of transcript>, ")?,
+        }
+        match self.actual {
+            Some(actual) => write!(f, "got {:?}", actual as char),
+            None => write!(f, "got <end of output>"),
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+#[derive(Debug)]
+pub struct Expected {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl Expected {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub fn check(&mut self, actual: u8, cycle: u64) -> Result<(), Divergence> {
+        let expected = self.bytes.get(self.position).copied();
+        if expected != Some(actual) {
+            return Err(Divergence {
+                cycle,
+                offset: self.position,
+                expected,
+                actual: Some(actual),
+            });
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+
+    pub fn finish(&self, cycle: u64) -> Result<(), Divergence> {
+        match self.bytes.get(self.position) {
+            Some(&expected) => Err(Divergence {
+                cycle,
+                offset: self.position,
+                expected: Some(expected),
+                actual: None,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn identical_transcripts_match() {
+    assert!(compare(b"a\nb\n", b"a\nb\n", false).is_none());
+}
+
+#[test]
+fn reports_first_differing_line() {
+    let mismatch = compare(b"one\ntwo\nthree\n", b"one\ntoo\nthree\n", false).unwrap();
+    assert_eq!(mismatch.offset, 5);
+    assert_eq!(mismatch.line, 2);
+    assert_eq!(mismatch.expected.as_deref(), Some("too"));
+    assert_eq!(mismatch.actual.as_deref(), Some("two"));
+}
+
+#[test]
+fn lenient_ignores_trailing_whitespace_and_diagnostics() {
+    let actual = b"hacking...\nroom  \nexits\n\n\n";
+    let expected = b"room\nexits";
+    assert!(compare(actual, expected, false).is_some());
+    assert!(compare(actual, expected, true).is_none());
+}
+
+#[test]
+fn reports_length_mismatch_at_end_of_shorter() {
+    let mismatch = compare(b"a\nb\n", b"a\nb\nc\n", false).unwrap();
+    assert_eq!(mismatch.offset, 4);
+    assert_eq!(mismatch.line, 3);
+    assert_eq!(mismatch.expected.as_deref(), Some("c"));
+    assert_eq!(mismatch.actual, None);
+}